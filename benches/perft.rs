@@ -0,0 +1,92 @@
+//! Perft throughput benchmark over the standard perft test positions.
+//!
+//! Run with `cargo bench --bench perft`, adding `--features pext` on CPUs with BMI2.
+
+use std::{
+    hint::black_box,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use athena::chess::{Position, movegen::init_movegen, perft::perft};
+
+/// Number of times each position is searched, keeping the fastest to reduce timing noise.
+const RUNS: u32 = 5;
+
+/// The six positions from <https://www.chessprogramming.org/Perft_Results>, with the depth to
+/// benchmark each at and the expected node count.
+const POSITIONS: [(&str, &str, u32, u64); 6] = [
+    (
+        "startpos",
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        5,
+        4865609,
+    ),
+    (
+        "kiwipete",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        4,
+        4085603,
+    ),
+    (
+        "position 3",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        6,
+        11030083,
+    ),
+    (
+        "position 4",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        5,
+        15833292,
+    ),
+    (
+        "position 5",
+        "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        4,
+        2103487,
+    ),
+    (
+        "position 6",
+        "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+        4,
+        3894594,
+    ),
+];
+
+fn nodes_per_second(nodes: u64, elapsed: Duration) -> u64 {
+    (nodes as f64 / elapsed.as_secs_f64()) as u64
+}
+
+fn main() {
+    init_movegen();
+
+    let mut total_nodes = 0;
+    let mut total_time = Duration::ZERO;
+    for (name, fen, depth, expected) in POSITIONS {
+        let position = Position::from_str(fen).expect("benchmark FEN is valid");
+        let mut elapsed = Duration::MAX;
+        let mut nodes = 0;
+        for _ in 0..RUNS {
+            let start = Instant::now();
+            nodes = black_box(perft(&position, depth));
+            elapsed = elapsed.min(start.elapsed());
+            assert_eq!(expected, nodes, "perft mismatch for {name}");
+        }
+
+        println!(
+            "{name:<12} depth {depth}  {nodes:>10} nodes  {:>8.3}s  {:>10} nps",
+            elapsed.as_secs_f64(),
+            nodes_per_second(nodes, elapsed)
+        );
+        total_nodes += nodes;
+        total_time += elapsed;
+    }
+
+    println!(
+        "{:<20}  {total_nodes:>10} nodes  {:>8.3}s  {:>10} nps",
+        "total",
+        total_time.as_secs_f64(),
+        nodes_per_second(total_nodes, total_time)
+    );
+}
@@ -1,10 +1,17 @@
 mod bitboard;
 mod core;
 mod error;
+mod game;
+mod magic;
 pub mod movegen;
+pub mod perft;
+pub mod pgn;
 mod position;
+mod san;
+mod zobrist;
 
 pub use bitboard::*;
 pub use core::*;
 pub use error::*;
+pub use game::*;
 pub use position::*;
@@ -94,6 +94,11 @@ impl Bitboard {
             }
     }
 
+    /// Returns the number of set squares.
+    pub fn count(&self) -> u32 {
+        self.0.count_ones()
+    }
+
     pub fn pop_lsb(&mut self) -> Option<Square> {
         let lsb = self.lsb();
         if let Some(lsb) = lsb {
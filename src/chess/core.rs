@@ -83,6 +83,10 @@ impl File {
         }
     }
 
+    pub fn distance(&self, other: &Self) -> i8 {
+        *self as i8 - *other as i8
+    }
+
     fn from_index(index: usize) -> Result<Self, Error> {
         match index {
             0 => Ok(Self::A),
@@ -495,6 +499,21 @@ impl TryFrom<usize> for PieceType {
     }
 }
 
+impl PieceType {
+    /// Returns the conventional material value of the piece type in centipawns, for weighing
+    /// exchanges. The king is valued at zero since it can never be traded.
+    pub const fn value(&self) -> i32 {
+        match self {
+            Self::Pawn => 100,
+            Self::Knight => 320,
+            Self::Bishop => 330,
+            Self::Rook => 500,
+            Self::Queen => 900,
+            Self::King => 0,
+        }
+    }
+}
+
 impl Display for PieceType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let piece_char = match self {
@@ -596,6 +615,25 @@ pub enum CastlingRights {
 }
 
 impl CastlingRights {
+    fn from_bits(bits: u8) -> CastlingRights {
+        match bits & 0b11 {
+            0b11 => CastlingRights::All,
+            0b01 => CastlingRights::QueenSide,
+            0b10 => CastlingRights::KingSide,
+            _ => CastlingRights::None,
+        }
+    }
+
+    /// Returns whether every right in `other` is included
+    pub fn contains(&self, other: CastlingRights) -> bool {
+        *self as u8 & other as u8 == other as u8
+    }
+
+    /// Returns the combination of both sets of rights
+    pub fn union(&self, other: CastlingRights) -> CastlingRights {
+        Self::from_bits(*self as u8 | other as u8)
+    }
+
     /// Updates castling rights to not include a given set of rights
     pub fn downgrade(&self, downgrade: CastlingRights) -> CastlingRights {
         let self_byte = *self as u8;
@@ -604,9 +642,7 @@ impl CastlingRights {
             return *self;
         }
 
-        let result_byte = self_byte ^ (self_byte & other_byte);
-
-        unsafe { std::mem::transmute(result_byte) }
+        Self::from_bits(self_byte ^ (self_byte & other_byte))
     }
 }
 
@@ -1,11 +1,161 @@
+use std::fmt::Display;
+
 use thiserror::Error;
 
+use crate::chess::{Color, Square};
+
 #[derive(Debug, Error)]
 pub enum Error {
-    #[error("Invalid FEN String")]
-    InvalidFen,
+    #[error("Invalid FEN {field} at offset {offset}: {reason}")]
+    InvalidFen {
+        field: FenField,
+        offset: usize,
+        reason: FenError,
+    },
+    #[error("Invalid SAN move '{san}': {reason}")]
+    InvalidSan { san: String, reason: SanError },
+    #[error("Invalid UCI move '{uci}': {reason}")]
+    InvalidUciMove { uci: String, reason: UciMoveError },
+    #[error("Invalid PGN at line {line}: {reason}")]
+    InvalidPgn { line: usize, reason: PgnError },
+    #[error("Invalid perft suite entry '{0}'")]
+    InvalidPerftEntry(String),
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
     #[error("Parse error")]
     ParseError,
     #[error("Invalid Index")]
     InvalidIndex,
 }
+
+/// A field of a FEN string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FenField {
+    Record,
+    PiecePlacement,
+    SideToMove,
+    Castling,
+    EnPassant,
+    HalfMoveClock,
+    FullMoveNumber,
+}
+
+impl Display for FenField {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            FenField::Record => "record",
+            FenField::PiecePlacement => "piece placement",
+            FenField::SideToMove => "side to move",
+            FenField::Castling => "castling rights",
+            FenField::EnPassant => "en passant square",
+            FenField::HalfMoveClock => "halfmove clock",
+            FenField::FullMoveNumber => "fullmove number",
+        };
+
+        write!(f, "{name}")
+    }
+}
+
+/// Reason a FEN string was rejected.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum FenError {
+    #[error("non-ASCII character")]
+    NonAscii,
+    #[error("expected 6 fields, found {0}")]
+    FieldCount(usize),
+    #[error("expected 8 ranks, found {0}")]
+    RankCount(usize),
+    #[error("rank {rank} describes {squares} squares instead of 8")]
+    RankSize { rank: usize, squares: usize },
+    #[error("invalid empty square count '{0}'")]
+    InvalidDigit(char),
+    #[error("consecutive empty square counts")]
+    ConsecutiveDigits,
+    #[error("invalid piece '{0}'")]
+    InvalidPiece(char),
+    #[error("expected one {color:?} king, found {count}")]
+    KingCount { color: Color, count: u32 },
+    #[error("{color:?} has {count} pawns")]
+    PawnCount { color: Color, count: u32 },
+    #[error("pawn on back rank square {0}")]
+    PawnOnBackRank(Square),
+    #[error("side not to move is in check")]
+    OpponentInCheck,
+    #[error("expected 'w' or 'b', found '{0}'")]
+    InvalidSideToMove(String),
+    #[error("invalid castling character '{0}'")]
+    InvalidCastlingChar(char),
+    #[error("castling right '{0}' is repeated or out of order")]
+    CastlingOrder(char),
+    #[error("castling right '{0}' requires king and rook on their home squares")]
+    CastlingWithoutPieces(char),
+    #[error("invalid square '{0}'")]
+    InvalidSquare(String),
+    #[error("en passant square {0} is not on the side to move's sixth rank")]
+    EnPassantRank(Square),
+    #[error("no pawn can have just double pushed past en passant square {0}")]
+    EnPassantWithoutPawn(Square),
+    #[error("halfmove clock must be 0 after a double pawn push")]
+    EnPassantClock,
+    #[error("invalid number '{0}'")]
+    InvalidNumber(String),
+    #[error("fullmove number must be at least 1")]
+    FullMoveZero,
+}
+
+/// Reason a SAN move was rejected.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum SanError {
+    #[error("not valid SAN syntax")]
+    Syntax,
+    #[error("no legal move matches")]
+    Illegal,
+    #[error("a promotion piece must be given")]
+    MissingPromotion,
+    #[error("ambiguous between {}", .0.join(", "))]
+    Ambiguous(Vec<String>),
+}
+
+/// Reason a move in UCI coordinate notation was rejected.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum UciMoveError {
+    #[error("expected <from><to>[promotion], e.g. 'e2e4' or 'e7e8q'")]
+    Syntax,
+    #[error("no piece on {0}")]
+    EmptySquare(Square),
+    #[error("the piece on {0} belongs to the side not to move")]
+    WrongColor(Square),
+    #[error("a pawn reaching the last rank must name a promotion piece")]
+    MissingPromotion,
+    #[error("only a pawn reaching the last rank can promote")]
+    UnexpectedPromotion,
+    #[error("the piece cannot move there")]
+    Illegal,
+    #[error("the move leaves the king in check")]
+    KingInCheck,
+}
+
+/// Reason a PGN game was rejected.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum PgnError {
+    #[error("malformed tag pair")]
+    InvalidTag,
+    #[error("invalid FEN tag '{fen}': {reason}")]
+    InvalidFen { fen: String, reason: FenError },
+    #[error("unterminated comment")]
+    UnterminatedComment,
+    #[error("unexpected character '{0}'")]
+    UnexpectedChar(char),
+    #[error("')' without a matching '('")]
+    UnmatchedParenthesis,
+    #[error("variation is not closed before the end of the game")]
+    UnclosedVariation,
+    #[error("variation does not follow a move")]
+    VariationWithoutMove,
+    #[error("annotation glyph does not follow a move")]
+    NagWithoutMove,
+    #[error("invalid annotation glyph '{0}'")]
+    InvalidNag(String),
+    #[error("invalid move '{san}': {reason}")]
+    InvalidMove { san: String, reason: SanError },
+}
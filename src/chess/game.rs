@@ -0,0 +1,309 @@
+use crate::chess::{
+    Color, Position, State,
+    movegen::{Move, generate_legal_moves},
+    pgn::GameResult,
+};
+
+/// Why a game ended in a draw.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DrawReason {
+    Stalemate,
+    InsufficientMaterial,
+    /// The same position occurred five times, which ends the game without a claim.
+    FivefoldRepetition,
+    /// 75 moves by each side without a capture or pawn move, which ends the game without a claim.
+    SeventyFiveMoveRule,
+    /// The same position occurred three times, which entitles either player to claim a draw.
+    ThreefoldRepetition,
+    /// 50 moves by each side without a capture or pawn move, which entitles either player to
+    /// claim a draw.
+    FiftyMoveRule,
+}
+
+/// How a game ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Outcome {
+    Checkmate { winner: Color },
+    Draw(DrawReason),
+}
+
+impl From<Outcome> for GameResult {
+    fn from(outcome: Outcome) -> Self {
+        match outcome {
+            Outcome::Checkmate {
+                winner: Color::White,
+            } => GameResult::WhiteWins,
+            Outcome::Checkmate {
+                winner: Color::Black,
+            } => GameResult::BlackWins,
+            Outcome::Draw(_) => GameResult::Draw,
+        }
+    }
+}
+
+/// A game played from a starting position, tracking what a single [`Position`] cannot: the
+/// moves that led to it and the positions that occurred along the way.
+#[derive(Debug, Clone)]
+pub struct Game {
+    position: Position,
+    history: Vec<State>,
+    moves: Vec<Move>,
+    /// Hashes of the positions before each move, used to detect repetitions.
+    hashes: Vec<u64>,
+}
+
+impl Game {
+    pub fn new(position: Position) -> Self {
+        Self {
+            position,
+            history: Vec::new(),
+            moves: Vec::new(),
+            hashes: Vec::new(),
+        }
+    }
+
+    pub fn position(&self) -> &Position {
+        &self.position
+    }
+
+    /// Returns the moves played since the starting position.
+    pub fn moves(&self) -> &[Move] {
+        &self.moves
+    }
+
+    /// Plays a move, returning `false` and leaving the game unchanged if it is not legal.
+    pub fn push_move(&mut self, mv: Move) -> bool {
+        if !generate_legal_moves(&self.position)
+            .into_iter()
+            .any(|legal| legal == mv)
+        {
+            return false;
+        }
+
+        self.hashes.push(self.position.hash());
+        self.position.make_move(mv, &mut self.history);
+        self.moves.push(mv);
+        true
+    }
+
+    /// Takes back the last move, returning it, or `None` at the starting position.
+    pub fn pop_move(&mut self) -> Option<Move> {
+        let mv = self.moves.pop()?;
+        self.position.unmake_move(mv, &mut self.history);
+        self.hashes.pop();
+        Some(mv)
+    }
+
+    /// Returns how many times the current position has occurred, counting this occurrence.
+    ///
+    /// Positions are compared by hash, so they match when the pieces, side to move, castling
+    /// rights and capturable en passant square do.
+    pub fn repetitions(&self) -> usize {
+        let hash = self.position.hash();
+        let earlier = self
+            .hashes
+            .iter()
+            .rev()
+            .take(self.position.half_move_clock() as usize)
+            .skip(1)
+            .step_by(2)
+            .filter(|&&earlier| earlier == hash)
+            .count();
+
+        earlier + 1
+    }
+
+    /// Returns how the game ended, or `None` if it goes on.
+    ///
+    /// Claimable draws by threefold repetition and the 50-move rule are reported as if claimed.
+    /// Checkmate takes precedence over every draw, including one by the 75-move rule.
+    pub fn result(&self) -> Option<Outcome> {
+        let side_to_move = self.position.side_to_move();
+        if generate_legal_moves(&self.position).is_empty() {
+            return Some(if self.position.is_checked(side_to_move) {
+                Outcome::Checkmate {
+                    winner: !side_to_move,
+                }
+            } else {
+                Outcome::Draw(DrawReason::Stalemate)
+            });
+        }
+
+        let repetitions = self.repetitions();
+        let half_move_clock = self.position.half_move_clock();
+        let reason = if self.position.has_insufficient_material() {
+            DrawReason::InsufficientMaterial
+        } else if repetitions >= 5 {
+            DrawReason::FivefoldRepetition
+        } else if half_move_clock >= 150 {
+            DrawReason::SeventyFiveMoveRule
+        } else if repetitions >= 3 {
+            DrawReason::ThreefoldRepetition
+        } else if half_move_clock >= 100 {
+            DrawReason::FiftyMoveRule
+        } else {
+            return None;
+        };
+
+        Some(Outcome::Draw(reason))
+    }
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Self::new(Position::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::chess::STARTING_FEN;
+
+    use super::*;
+
+    const OPEN_GAME: &str = "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2";
+
+    fn game(fen: &str, sans: &[&str]) -> Game {
+        let mut game = Game::new(Position::from_str(fen).unwrap());
+        for san in sans {
+            let mv = Move::from_san(san, game.position()).unwrap();
+            assert!(game.push_move(mv), "{san}");
+        }
+
+        game
+    }
+
+    fn shuffle(times: usize) -> Vec<&'static str> {
+        ["Nf3", "Nf6", "Ng1", "Ng8"].repeat(times)
+    }
+
+    #[test]
+    fn test_push_and_pop() {
+        let mut game = Game::default();
+        let e4 = Move::from_san("e4", game.position()).unwrap();
+        let illegal = Move::from_uci("e2e4", &Position::default()).unwrap();
+
+        assert!(game.push_move(e4));
+        assert!(!game.push_move(illegal));
+        assert_eq!(&[e4], game.moves());
+        assert_eq!(Some(e4), game.pop_move());
+        assert_eq!(None, game.pop_move());
+        assert_eq!(&Position::default(), game.position());
+    }
+
+    #[test]
+    fn test_checkmate_and_stalemate() {
+        let mate = game(STARTING_FEN, &["f3", "e5", "g4", "Qh4"]);
+        assert_eq!(
+            Some(Outcome::Checkmate {
+                winner: Color::Black
+            }),
+            mate.result()
+        );
+        assert_eq!(
+            GameResult::BlackWins,
+            GameResult::from(mate.result().unwrap())
+        );
+
+        let stalemate = game("7k/8/6Q1/8/8/8/8/K7 w - - 0 1", &["Qf7"]);
+        assert_eq!(
+            Some(Outcome::Draw(DrawReason::Stalemate)),
+            stalemate.result()
+        );
+        assert_eq!(None, Game::default().result());
+    }
+
+    #[test]
+    fn test_repetition() {
+        let mut game = game(STARTING_FEN, &shuffle(2));
+        assert_eq!(3, game.repetitions());
+        assert_eq!(
+            Some(Outcome::Draw(DrawReason::ThreefoldRepetition)),
+            game.result()
+        );
+
+        game.pop_move();
+        assert_eq!(2, game.repetitions());
+        assert_eq!(None, game.result());
+
+        let game = self::game(STARTING_FEN, &shuffle(4));
+        assert_eq!(
+            Some(Outcome::Draw(DrawReason::FivefoldRepetition)),
+            game.result()
+        );
+
+        // The en passant square of the starting FEN cannot be captured on, so the position
+        // repeats once the knights return
+        let moves = ["Nf6", "Nf3", "Ng8", "Ng1"].repeat(2);
+        let game = self::game(
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+            &moves,
+        );
+        assert_eq!(3, game.repetitions());
+        assert_eq!(
+            Some(Outcome::Draw(DrawReason::ThreefoldRepetition)),
+            game.result()
+        );
+    }
+
+    #[test]
+    fn test_repetition_requires_same_castling_rights() {
+        let moves = ["Ke2", "Ke7", "Ke1", "Ke8"].repeat(2);
+        let game = game(OPEN_GAME, &moves);
+
+        assert_eq!(2, game.repetitions());
+        assert_eq!(None, game.result());
+    }
+
+    #[test]
+    fn test_move_rules() {
+        let fifty = game("4k3/8/8/8/8/8/8/R3K3 w - - 99 80", &["Ra2"]);
+        assert_eq!(
+            Some(Outcome::Draw(DrawReason::FiftyMoveRule)),
+            fifty.result()
+        );
+
+        let seventy_five = game("4k3/8/8/8/8/8/8/R3K3 w - - 149 80", &["Ra2"]);
+        assert_eq!(
+            Some(Outcome::Draw(DrawReason::SeventyFiveMoveRule)),
+            seventy_five.result()
+        );
+
+        let mate = game("4k3/8/4K3/8/8/8/8/R7 w - - 149 80", &["Ra8"]);
+        assert_eq!(
+            Some(Outcome::Checkmate {
+                winner: Color::White
+            }),
+            mate.result()
+        );
+    }
+
+    #[test]
+    fn test_insufficient_material() {
+        for (fen, expected) in [
+            ("4k3/8/8/8/8/8/8/4K3 w - - 0 1", true),
+            ("4k3/8/8/8/8/8/8/4KN2 w - - 0 1", true),
+            ("4k3/8/8/8/8/8/8/4KB2 w - - 0 1", true),
+            ("4kb2/8/8/8/8/8/8/2B1K3 w - - 0 1", true),
+            ("4k1b1/8/8/8/8/8/8/2B1K3 w - - 0 1", false),
+            ("4k3/8/8/8/8/8/8/3NKN2 w - - 0 1", false),
+            ("4kn2/8/8/8/8/8/8/4KB2 w - - 0 1", false),
+            ("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1", false),
+            ("4k3/8/8/8/8/8/8/4K2R w - - 0 1", false),
+        ] {
+            let game = game(fen, &[]);
+            assert_eq!(
+                expected,
+                game.position().has_insufficient_material(),
+                "{fen}"
+            );
+            assert_eq!(
+                expected,
+                game.result() == Some(Outcome::Draw(DrawReason::InsufficientMaterial)),
+                "{fen}"
+            );
+        }
+    }
+}
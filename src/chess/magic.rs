@@ -0,0 +1,371 @@
+use lazy_static::{initialize, lazy_static};
+
+use crate::chess::{Bitboard, Direction, File, NUM_SQUARES, Rank, Square};
+
+/// Number of rook attack sets over every square, with one per relevant occupancy.
+const ROOK_TABLE_SIZE: usize = 102_400;
+/// Number of bishop attack sets over every square, with one per relevant occupancy.
+const BISHOP_TABLE_SIZE: usize = 5_248;
+
+const ROOK_DIRECTIONS: [Direction; 4] = [
+    Direction::North,
+    Direction::South,
+    Direction::East,
+    Direction::West,
+];
+const BISHOP_DIRECTIONS: [Direction; 4] = [
+    Direction::NorthEast,
+    Direction::NorthWest,
+    Direction::SouthEast,
+    Direction::SouthWest,
+];
+
+/// Magic multipliers for rooks, indexed by square.
+///
+/// These were found by a random search over sparse candidates and map every relevant occupancy
+/// into a table of `2^bits` entries, where `bits` is the size of the square's blocker mask.
+const ROOK_MAGIC_NUMBERS: [u64; NUM_SQUARES] = [
+    0x8080_0064_9040_0880,
+    0x0040_0010_0020_0040,
+    0x4200_100a_0080_2040,
+    0x4880_1002_0480_0800,
+    0x0200_0408_0200_1020,
+    0x4100_0801_0002_0400,
+    0x1300_0500_0600_008c,
+    0x0100_0091_2300_0242,
+    0x0000_8000_2080_4000,
+    0x0000_4010_0020_0040,
+    0x0160_8020_0880_1000,
+    0x0401_8010_0080_4800,
+    0x0820_8008_0204_0080,
+    0x2002_0010_0805_0200,
+    0x8801_0001_0004_0200,
+    0x000e_0004_0065_008e,
+    0x5100_8080_0040_0020,
+    0x0010_0040_0020_0048,
+    0x480c_8080_2004_1003,
+    0x8390_0080_0800_8010,
+    0x1001_0100_0800_0410,
+    0x8043_8080_0200_0400,
+    0x4000_0400_c802_1001,
+    0x0080_2200_1084_c104,
+    0x0140_8024_8001_c00c,
+    0x8000_2002_8040_0083,
+    0x0000_2001_0011_0040,
+    0x1008_0488_8010_0180,
+    0x0280_0400_8080_0800,
+    0x0100_0200_8004_0080,
+    0x0010_7004_0011_0a08,
+    0x00a0_0042_0001_00a4,
+    0x0200_8040_0080_0020,
+    0x0000_8022_0200_4100,
+    0x0480_1000_8080_2000,
+    0x8040_8008_0080_1000,
+    0x4800_8008_0080_0400,
+    0x8804_0010_0202_0008,
+    0x2580_2110_0400_0298,
+    0x0002_1090_6200_0401,
+    0x0000_8003_c004_8020,
+    0x00c0_5004_2008_4000,
+    0x0084_8440_1202_0020,
+    0x4101_0010_0009_0021,
+    0x0008_0004_0200_4040,
+    0x2408_0200_0400_8080,
+    0x0200_0210_0804_0001,
+    0x8000_0040_a402_0001,
+    0x0040_8001_1020_4500,
+    0x0820_2000_9040_0080,
+    0x1200_4020_0100_1100,
+    0x0010_0009_00a0_1500,
+    0x0020_8004_0008_0080,
+    0x601a_001c_0890_2600,
+    0x4001_0042_0004_2100,
+    0x10b1_00a1_0400_4200,
+    0x0800_2010_8042_0902,
+    0x0040_8020_400c_1101,
+    0x8883_0010_400a_2001,
+    0x0020_2190_4006_0602,
+    0x0101_0082_0800_1005,
+    0x0132_0001_1008_0482,
+    0x0189_0000_8402_0041,
+    0x9008_0820_4400_8502,
+];
+
+/// Magic multipliers for bishops, indexed by square.
+const BISHOP_MAGIC_NUMBERS: [u64; NUM_SQUARES] = [
+    0x0804_5004_0040_8202,
+    0x0604_1404_0442_0862,
+    0x4038_8281_0602_2000,
+    0x2464_2420_80c0_0000,
+    0x0888_4840_8800_c030,
+    0x12a2_2820_0821_0042,
+    0x0000_8084_1140_0000,
+    0x8050_1484_0420_0450,
+    0x6041_5024_0144_4400,
+    0x0000_0818_4884_0040,
+    0x0024_0828_4500_2002,
+    0x8002_188a_0200_00a4,
+    0x0002_2202_1124_0200,
+    0x0000_2110_0804_00a0,
+    0x8010_a082_0120_2000,
+    0x0001_0080_8090_1080,
+    0x0008_0040_0208_1244,
+    0x0020_0203_0404_0683,
+    0xa002_0019_0401_0200,
+    0x2090_8008_0205_4015,
+    0x200c_0004_80a0_8008,
+    0x0002_0201_0801_1402,
+    0x0041_0880_9818_0200,
+    0x0242_0300_c12c_8801,
+    0x3010_4021_0608_8200,
+    0x0050_2c00_5214_8420,
+    0x1080_3004_0808_4040,
+    0x0020_1100_0800_4004,
+    0x0a31_00d0_1100_c000,
+    0x0018_2200_1909_0100,
+    0x0448_4a02_0080_a400,
+    0x0061_0440_20ae_0820,
+    0xc822_1004_1010_2008,
+    0x000b_0c10_0020_0180,
+    0x920c_0448_0001_020a,
+    0x2082_0040_410c_0100,
+    0x1820_1084_0040_8020,
+    0x0204_0812_0100_2082,
+    0x4854_1120_4014_0c00,
+    0x2084_0042_1400_4501,
+    0x4142_0120_6120_0843,
+    0x88c2_0822_0900_8220,
+    0x00ce_4200_5000_0100,
+    0x0806_0120_1800_9101,
+    0x4010_0401_0a04_b400,
+    0x000d_0408_0290_0200,
+    0x1004_0800_c40a_0503,
+    0x0250_4420_8080_8820,
+    0x0c80_8801_0820_3000,
+    0x2106_0044_5404_8002,
+    0x0340_0884_2088_000a,
+    0x0010_1100_2088_08b3,
+    0x0100_0044_6082_0101,
+    0x0080_0450_0212_0284,
+    0x1010_0481_0404_0108,
+    0x2010_9000_a080_8422,
+    0x0801_0082_0141_4004,
+    0x8a00_4421_0108_a000,
+    0x0400_0001_0080_9004,
+    0x0200_0200_0420_8800,
+    0x4840_4000_0420_8208,
+    0x0800_0020_0410_2080,
+    0x00a0_0408_2888_0489,
+    0x0102_1002_0c84_0480,
+];
+
+#[cfg(all(
+    feature = "pext",
+    not(all(target_arch = "x86_64", target_feature = "bmi2"))
+))]
+compile_error!(
+    "the `pext` feature requires BMI2, build with RUSTFLAGS=\"-C target-feature=+bmi2\" or \"-C target-cpu=native\""
+);
+
+lazy_static! {
+    static ref ROOK_MAGICS: MagicTable =
+        MagicTable::new(&ROOK_DIRECTIONS, &ROOK_MAGIC_NUMBERS, ROOK_TABLE_SIZE);
+    static ref BISHOP_MAGICS: MagicTable =
+        MagicTable::new(&BISHOP_DIRECTIONS, &BISHOP_MAGIC_NUMBERS, BISHOP_TABLE_SIZE);
+}
+
+/// Builds the sliding attack tables ahead of their first use.
+pub(crate) fn init_magics() {
+    initialize(&ROOK_MAGICS);
+    initialize(&BISHOP_MAGICS);
+}
+
+pub(crate) fn rook_attacks(square: Square, occupied: Bitboard) -> Bitboard {
+    ROOK_MAGICS.attacks(square, occupied)
+}
+
+pub(crate) fn bishop_attacks(square: Square, occupied: Bitboard) -> Bitboard {
+    BISHOP_MAGICS.attacks(square, occupied)
+}
+
+/// Maps the occupancy of a square's relevant blockers to an index in the shared attack table.
+///
+/// With the `pext` feature the blockers are gathered into the low bits with BMI2 `PEXT`,
+/// otherwise a [magic multiplication](https://www.chessprogramming.org/Magic_Bitboards) hashes
+/// them without collisions between occupancies that have different attacks.
+#[derive(Debug, Clone, Copy, Default)]
+struct Magic {
+    mask: u64,
+    #[cfg(not(feature = "pext"))]
+    magic: u64,
+    #[cfg(not(feature = "pext"))]
+    shift: u32,
+    offset: usize,
+}
+
+impl Magic {
+    #[cfg(not(feature = "pext"))]
+    fn index(&self, occupied: Bitboard) -> usize {
+        self.offset + ((occupied.0 & self.mask).wrapping_mul(self.magic) >> self.shift) as usize
+    }
+
+    #[cfg(feature = "pext")]
+    fn index(&self, occupied: Bitboard) -> usize {
+        // SAFETY: the `pext` feature only compiles when BMI2 is enabled for the target
+        self.offset + unsafe { std::arch::x86_64::_pext_u64(occupied.0, self.mask) } as usize
+    }
+}
+
+/// Precomputed attacks of one sliding piece type for every square and occupancy.
+struct MagicTable {
+    magics: [Magic; NUM_SQUARES],
+    attacks: Vec<Bitboard>,
+}
+
+impl MagicTable {
+    fn new(
+        directions: &[Direction],
+        #[cfg_attr(feature = "pext", expect(unused_variables))] magic_numbers: &[u64; NUM_SQUARES],
+        table_size: usize,
+    ) -> Self {
+        let mut table = Self {
+            magics: [Magic::default(); NUM_SQUARES],
+            attacks: vec![Bitboard::EMPTY; table_size],
+        };
+
+        let mut offset = 0;
+        for square in Square::values() {
+            let mask = relevant_occupancy(square, directions);
+            let magic = Magic {
+                mask: mask.0,
+                #[cfg(not(feature = "pext"))]
+                magic: magic_numbers[square.lsf_index()],
+                #[cfg(not(feature = "pext"))]
+                shift: 64 - mask.count(),
+                offset,
+            };
+            table.magics[square.lsf_index()] = magic;
+
+            // Enumerate every subset of the mask with the Carry-Rippler trick
+            let mut occupied = Bitboard::EMPTY;
+            loop {
+                let attacks = sliding_attacks(square, occupied, directions);
+                let entry = &mut table.attacks[magic.index(occupied)];
+                debug_assert!(
+                    *entry == Bitboard::EMPTY || *entry == attacks,
+                    "magic for {square} maps occupancies with different attacks together"
+                );
+                *entry = attacks;
+
+                occupied = Bitboard(occupied.0.wrapping_sub(mask.0) & mask.0);
+                if occupied == Bitboard::EMPTY {
+                    break;
+                }
+            }
+
+            offset += 1 << mask.count();
+        }
+
+        debug_assert_eq!(table_size, offset);
+        table
+    }
+
+    fn attacks(&self, square: Square, occupied: Bitboard) -> Bitboard {
+        self.attacks[self.magics[square.lsf_index()].index(occupied)]
+    }
+}
+
+/// Returns the squares whose occupancy can change a slider's attacks from `square`.
+///
+/// The last square of every ray is left out, as a piece there blocks nothing beyond it.
+fn relevant_occupancy(square: Square, directions: &[Direction]) -> Bitboard {
+    let edges = ((Bitboard::from(Rank::One) | Bitboard::from(Rank::Eight))
+        & !Bitboard::from(square.rank()))
+        | ((Bitboard::from(File::A) | Bitboard::from(File::H)) & !Bitboard::from(square.file()));
+
+    sliding_attacks(square, Bitboard::EMPTY, directions) & !edges
+}
+
+/// Computes slider attacks by walking each ray until it leaves the board or hits a blocker.
+fn sliding_attacks(square: Square, occupied: Bitboard, directions: &[Direction]) -> Bitboard {
+    let mut attacks = Bitboard::EMPTY;
+    for &direction in directions {
+        let mut ray = Bitboard::from(square);
+        loop {
+            ray = ray.shift(direction) & !wrapped_file(direction);
+            if ray == Bitboard::EMPTY {
+                break;
+            }
+
+            attacks |= ray;
+            if ray & occupied != Bitboard::EMPTY {
+                break;
+            }
+        }
+    }
+
+    attacks
+}
+
+/// Returns the file a piece lands on when a shift in `direction` wraps around the board.
+fn wrapped_file(direction: Direction) -> Bitboard {
+    match direction {
+        Direction::East | Direction::NorthEast | Direction::SouthEast => File::A.into(),
+        Direction::West | Direction::NorthWest | Direction::SouthWest => File::H.into(),
+        Direction::North | Direction::South => Bitboard::EMPTY,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn random_occupancies() -> impl Iterator<Item = Bitboard> {
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        std::iter::repeat_with(move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            Bitboard(state & (state >> 3))
+        })
+        .take(256)
+    }
+
+    #[test]
+    fn test_rook_attacks_match_rays() {
+        for square in Square::values() {
+            for occupied in random_occupancies() {
+                assert_eq!(
+                    sliding_attacks(square, occupied, &ROOK_DIRECTIONS),
+                    rook_attacks(square, occupied),
+                    "{square}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_bishop_attacks_match_rays() {
+        for square in Square::values() {
+            for occupied in random_occupancies() {
+                assert_eq!(
+                    sliding_attacks(square, occupied, &BISHOP_DIRECTIONS),
+                    bishop_attacks(square, occupied),
+                    "{square}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_relevant_occupancy() {
+        assert_eq!(
+            Bitboard(0x0001_0101_0101_017e),
+            relevant_occupancy(Square::A1, &ROOK_DIRECTIONS)
+        );
+        assert_eq!(
+            Bitboard(0x0040_2010_0804_0200),
+            relevant_occupancy(Square::A1, &BISHOP_DIRECTIONS)
+        );
+    }
+}
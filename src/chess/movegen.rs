@@ -1,12 +1,14 @@
 use lazy_static::{initialize, lazy_static};
 
 use crate::chess::{
-    Bitboard, CastlingRights, Color, Direction, File, NUM_COLORS, NUM_SQUARES, Piece, PieceType,
-    Position, Rank, Square,
+    Bitboard, CastlingRights, Color, Direction, Error, File, NUM_COLORS, NUM_SQUARES, Piece,
+    PieceType, Position, Rank, Square, UciMoveError,
+    magic::{self, init_magics},
+    zobrist::init_zobrist,
 };
 
 const NUM_ADJACENT_SQUARES: usize = 8;
-const MAX_MOVES: usize = 256;
+pub(crate) const MAX_MOVES: usize = 256;
 
 lazy_static! {
     static ref PAWN_ATTACK_MASKS: [[Bitboard; NUM_SQUARES]; NUM_COLORS] = gen_pawn_attack_masks();
@@ -14,6 +16,8 @@ lazy_static! {
     static ref KNIGHT_ATTACK_MASKS: [Bitboard; NUM_SQUARES] = gen_knight_masks();
     static ref SLIDING_ATTACK_MASKS: [[Bitboard; NUM_SQUARES]; NUM_ADJACENT_SQUARES] =
         gen_sliding_masks();
+    static ref BETWEEN_MASKS: [[Bitboard; NUM_SQUARES]; NUM_SQUARES] = gen_between_masks();
+    static ref LINE_MASKS: [[Bitboard; NUM_SQUARES]; NUM_SQUARES] = gen_line_masks();
 }
 
 /// Initializes global settings for move generation.
@@ -25,6 +29,10 @@ pub fn init_movegen() {
     initialize(&KNIGHT_ATTACK_MASKS);
     initialize(&SLIDING_ATTACK_MASKS);
     initialize(&KING_ATTACK_MASKS);
+    initialize(&BETWEEN_MASKS);
+    initialize(&LINE_MASKS);
+    init_magics();
+    init_zobrist();
 }
 
 /// Helper class for storing a list of moves for a particular position
@@ -32,14 +40,14 @@ pub fn init_movegen() {
 /// This class contains a list of moves associated with a position. Moves are stored
 /// in a pre-allocated array on the stack to help with performance.
 pub struct MoveList {
-    moves: [Option<Move>; MAX_MOVES],
+    moves: [Move; MAX_MOVES],
     len: usize,
 }
 
 impl MoveList {
     pub fn new() -> Self {
         Self {
-            moves: [None; MAX_MOVES],
+            moves: [Move::NULL; MAX_MOVES],
             len: 0,
         }
     }
@@ -55,7 +63,7 @@ impl MoveList {
 
     pub fn get(&self, index: usize) -> Option<Move> {
         if index < self.len() {
-            self.moves[index]
+            Some(self.moves[index])
         } else {
             None
         }
@@ -63,15 +71,44 @@ impl MoveList {
 
     pub fn push(&mut self, mv: Move) {
         debug_assert!(self.len() < self.moves.len());
-        self.moves[self.len()] = Some(mv);
+        self.moves[self.len()] = mv;
         self.len += 1;
     }
 
+    pub fn swap(&mut self, a: usize, b: usize) {
+        debug_assert!(a < self.len && b < self.len);
+        self.moves.swap(a, b);
+    }
+
+    pub fn as_slice(&self) -> &[Move] {
+        &self.moves[..self.len]
+    }
+
+    /// Moves the highest scoring move from `start` onwards to `start` and returns it, where
+    /// `scores[i]` is the score of the move at `i` and is swapped along with it.
+    ///
+    /// Calling this for successive `start` indices sorts the list one move at a time, so no
+    /// effort is spent ordering moves that a cutoff leaves unsearched.
+    pub fn select_best(&mut self, start: usize, scores: &mut [i32]) -> Option<Move> {
+        if start >= self.len {
+            return None;
+        }
+
+        let mut best = start;
+        for i in start + 1..self.len {
+            if scores[i] > scores[best] {
+                best = i;
+            }
+        }
+
+        self.moves.swap(start, best);
+        scores.swap(start, best);
+        Some(self.moves[start])
+    }
+
     pub fn swap_remove(&mut self, index: usize) {
         debug_assert!(index < self.len);
         self.moves.swap(index, self.len - 1);
-
-        self.moves[self.len() - 1] = None;
         self.len -= 1;
     }
 }
@@ -105,9 +142,9 @@ impl Iterator for MoveListIter {
 
     fn next(&mut self) -> Option<Self::Item> {
         if self.next < self.list.len() {
-            let result = self.list.moves[self.next].take();
+            let result = self.list.moves[self.next];
             self.next += 1;
-            result
+            Some(result)
         } else {
             None
         }
@@ -137,6 +174,19 @@ fn diagonal_masks(square: Square) -> Bitboard {
         | SLIDING_ATTACK_MASKS[Direction::SouthWest.mask_cache_index()][square_index]
 }
 
+/// Returns the squares strictly between two squares sharing a rank, file or diagonal.
+///
+/// Squares that are not aligned have nothing between them.
+pub(crate) fn between(a: Square, b: Square) -> Bitboard {
+    BETWEEN_MASKS[a.lsf_index()][b.lsf_index()]
+}
+
+/// Returns the full rank, file or diagonal through two aligned squares, or an empty board if
+/// the squares are not aligned.
+pub(crate) fn line(a: Square, b: Square) -> Bitboard {
+    LINE_MASKS[a.lsf_index()][b.lsf_index()]
+}
+
 fn orthogonal_masks(square: Square) -> Bitboard {
     let square_index = square.lsf_index();
     SLIDING_ATTACK_MASKS[Direction::North.mask_cache_index()][square_index]
@@ -221,6 +271,40 @@ fn gen_sliding_masks() -> [[Bitboard; NUM_SQUARES]; NUM_ADJACENT_SQUARES] {
     sliding_masks
 }
 
+fn gen_between_masks() -> [[Bitboard; NUM_SQUARES]; NUM_SQUARES] {
+    let mut between_masks = [[Bitboard::EMPTY; NUM_SQUARES]; NUM_SQUARES];
+    for a in Square::values() {
+        for direction in Direction::ALL {
+            let ray = SLIDING_ATTACK_MASKS[direction.mask_cache_index()][a.lsf_index()];
+            let mut targets = ray;
+            while let Some(b) = targets.pop_lsb() {
+                let beyond = SLIDING_ATTACK_MASKS[direction.mask_cache_index()][b.lsf_index()];
+                between_masks[a.lsf_index()][b.lsf_index()] = ray & !beyond & !Bitboard::from(b);
+            }
+        }
+    }
+
+    between_masks
+}
+
+fn gen_line_masks() -> [[Bitboard; NUM_SQUARES]; NUM_SQUARES] {
+    let mut line_masks = [[Bitboard::EMPTY; NUM_SQUARES]; NUM_SQUARES];
+    for a in Square::values() {
+        for direction in Direction::ALL {
+            let cache_index = direction.mask_cache_index();
+            let line = SLIDING_ATTACK_MASKS[cache_index][a.lsf_index()]
+                | SLIDING_ATTACK_MASKS[direction.flip().mask_cache_index()][a.lsf_index()]
+                | Bitboard::from(a);
+            let mut targets = SLIDING_ATTACK_MASKS[cache_index][a.lsf_index()];
+            while let Some(b) = targets.pop_lsb() {
+                line_masks[a.lsf_index()][b.lsf_index()] = line;
+            }
+        }
+    }
+
+    line_masks
+}
+
 fn gen_knight_masks() -> [Bitboard; NUM_SQUARES] {
     let mut knight_moves = [Bitboard::EMPTY; NUM_SQUARES];
     for s in Square::values() {
@@ -253,6 +337,17 @@ fn gen_knight_masks() -> [Bitboard; NUM_SQUARES] {
 }
 
 impl Direction {
+    const ALL: [Direction; NUM_ADJACENT_SQUARES] = [
+        Direction::North,
+        Direction::South,
+        Direction::East,
+        Direction::West,
+        Direction::NorthEast,
+        Direction::NorthWest,
+        Direction::SouthEast,
+        Direction::SouthWest,
+    ];
+
     fn mask_cache_index(&self) -> usize {
         match self {
             Direction::North => 0,
@@ -265,13 +360,6 @@ impl Direction {
             Direction::SouthWest => 7,
         }
     }
-
-    fn is_negative(&self) -> bool {
-        matches!(
-            self,
-            Direction::South | Direction::SouthEast | Direction::SouthWest | Direction::West
-        )
-    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -283,57 +371,476 @@ pub enum MoveKind {
     Promotion(PieceType),
 }
 
+const SQUARE_MASK: u16 = 0x3f;
+const TO_SHIFT: u16 = 6;
+const FLAGS_SHIFT: u16 = 12;
+
+const QUIET_FLAGS: u16 = 0;
+const CAPTURE_FLAGS: u16 = 1;
+const EN_PASSANT_FLAGS: u16 = 2;
+const CASTLE_FLAGS: u16 = 3;
+/// Promotions add the promoted piece's offset from a knight to these flags.
+const PROMOTION_FLAGS: u16 = 4;
+
 /// A piece movement in chess.
 ///
 /// In chess, a move commonly refers to a piece movement from **both**
 /// sides. In chess programming, a move represents a single piece movement, which is
 /// otherwise called a half-move or a ply.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-pub struct Move {
-    from: Square,
-    to: Square,
-    kind: MoveKind,
-}
+///
+/// Moves are packed into 16 bits: 6 bits each for the from and to squares, then 4 bits of flags
+/// for the kind.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Move(u16);
 
 impl Move {
-    pub fn new(from: Square, to: Square, kind: MoveKind) -> Self {
-        Self { from, to, kind }
+    /// The null move, which passes the turn. No piece moves from a1 to a1, so it never equals a
+    /// real move and marks the absence of one where an `Option` would cost space.
+    pub const NULL: Self = Self(0);
+
+    pub const fn new(from: Square, to: Square, kind: MoveKind) -> Self {
+        let flags = match kind {
+            MoveKind::Quiet => QUIET_FLAGS,
+            MoveKind::Capture => CAPTURE_FLAGS,
+            MoveKind::EnPassant => EN_PASSANT_FLAGS,
+            MoveKind::Castle => CASTLE_FLAGS,
+            MoveKind::Promotion(piece_type) => {
+                PROMOTION_FLAGS + piece_type as u16 - PieceType::Knight as u16
+            }
+        };
+
+        Self(from as u16 | (to as u16) << TO_SHIFT | flags << FLAGS_SHIFT)
+    }
+
+    pub const fn is_null(&self) -> bool {
+        self.0 == Self::NULL.0
     }
 
     pub fn from_sq(&self) -> Square {
-        self.from
+        // The mask keeps the index within the 64 squares
+        unsafe { std::mem::transmute((self.0 & SQUARE_MASK) as u8) }
     }
 
     pub fn to_sq(&self) -> Square {
-        self.to
+        unsafe { std::mem::transmute((self.0 >> TO_SHIFT & SQUARE_MASK) as u8) }
     }
 
     pub fn kind(&self) -> MoveKind {
-        self.kind
+        match self.0 >> FLAGS_SHIFT {
+            QUIET_FLAGS => MoveKind::Quiet,
+            CAPTURE_FLAGS => MoveKind::Capture,
+            EN_PASSANT_FLAGS => MoveKind::EnPassant,
+            CASTLE_FLAGS => MoveKind::Castle,
+            4 => MoveKind::Promotion(PieceType::Knight),
+            5 => MoveKind::Promotion(PieceType::Bishop),
+            6 => MoveKind::Promotion(PieceType::Rook),
+            7 => MoveKind::Promotion(PieceType::Queen),
+            _ => unreachable!("invalid move flags"),
+        }
     }
 
+    /// Returns the packed 16-bit form of the move.
+    pub(crate) const fn to_bits(self) -> u16 {
+        self.0
+    }
+
+    /// Rebuilds a move from bits returned by [`Move::to_bits`].
+    pub(crate) const fn from_bits(bits: u16) -> Self {
+        Self(bits)
+    }
+
+    /// Returns the squares the king and rook land on when this castling move is made.
+    ///
+    /// Castling moves are encoded as the king moving onto its own rook, which works for Chess960
+    /// as well. Whatever their start squares, the king ends on the g or c file with the rook on
+    /// the inner side next to it.
+    pub(crate) fn castling_squares(&self) -> (Square, Square) {
+        debug_assert_eq!(self.kind(), MoveKind::Castle);
+        let rank = self.from_sq().rank();
+        if self.to_sq().file().distance(&self.from_sq().file()) > 0 {
+            (Square::new(File::G, rank), Square::new(File::F, rank))
+        } else {
+            (Square::new(File::C, rank), Square::new(File::D, rank))
+        }
+    }
+
+    /// Formats the move in standard UCI coordinate notation, e.g. `e2e4`, `e7e8q` or `e1g1`.
     pub fn to_uci_string(&self) -> String {
+        self.to_uci(false)
+    }
+
+    /// Formats the move in UCI coordinate notation.
+    ///
+    /// Castling is written as the king's move to its destination, e.g. `e1g1`, unless `chess960`
+    /// is set, in which case it is written as the king taking its own rook, e.g. `e1h1`.
+    pub fn to_uci(&self, chess960: bool) -> String {
         let promotion_char = if let MoveKind::Promotion(promotion_piece_type) = self.kind() {
             &promotion_piece_type.to_string()
         } else {
             ""
         };
+        let to = if self.kind() == MoveKind::Castle && !chess960 {
+            self.castling_squares().0
+        } else {
+            self.to_sq()
+        };
 
-        format!("{}{}{}", self.from_sq(), self.to_sq(), promotion_char)
+        format!("{}{}{}", self.from_sq(), to, promotion_char)
+    }
+
+    /// Parses a move in UCI coordinate notation, such as `e2e4` or `e7e8q`, inferring its kind
+    /// from `position`.
+    ///
+    /// Castling is accepted as the king taking its own rook, e.g. `e1h1`. Unless the position is
+    /// Chess960, where that would be ambiguous, the king's move to its destination, e.g. `e1g1`,
+    /// is accepted as well. The move must be legal.
+    pub fn from_uci(uci: &str, position: &Position) -> Result<Move, Error> {
+        let error = |reason| Error::InvalidUciMove {
+            uci: uci.to_string(),
+            reason,
+        };
+
+        if !uci.is_ascii() || !(4..=5).contains(&uci.len()) {
+            return Err(error(UciMoveError::Syntax));
+        }
+
+        let from: Square = uci[0..2].parse().map_err(|_| error(UciMoveError::Syntax))?;
+        let to: Square = uci[2..4].parse().map_err(|_| error(UciMoveError::Syntax))?;
+        let promotion = match &uci[4..] {
+            "" => None,
+            "n" | "b" | "r" | "q" => Some(
+                uci[4..]
+                    .parse::<PieceType>()
+                    .map_err(|_| error(UciMoveError::Syntax))?,
+            ),
+            _ => return Err(error(UciMoveError::Syntax)),
+        };
+
+        let us = position.side_to_move();
+        let piece = position
+            .get_piece_at(&from)
+            .ok_or_else(|| error(UciMoveError::EmptySquare(from)))?;
+        if piece.color() != us {
+            return Err(error(UciMoveError::WrongColor(from)));
+        }
+
+        let last_rank = if us.is_white() {
+            Rank::Eight
+        } else {
+            Rank::One
+        };
+        let promotes = piece.piece_type() == PieceType::Pawn && to.rank() == last_rank;
+        let kind = match (promotes, promotion) {
+            (true, Some(promotion)) => MoveKind::Promotion(promotion),
+            (true, None) => return Err(error(UciMoveError::MissingPromotion)),
+            (false, Some(_)) => return Err(error(UciMoveError::UnexpectedPromotion)),
+            (false, None) => match piece.piece_type() {
+                PieceType::King
+                    if position.get_piece_at(&to) == Some(Piece::new(us, PieceType::Rook)) =>
+                {
+                    MoveKind::Castle
+                }
+                PieceType::King
+                    if !position.is_chess960() && from.file().distance(&to.file()).abs() == 2 =>
+                {
+                    let side = if to.file().distance(&from.file()) > 0 {
+                        CastlingRights::KingSide
+                    } else {
+                        CastlingRights::QueenSide
+                    };
+                    let rook = position
+                        .castling_rook(us, side)
+                        .ok_or_else(|| error(UciMoveError::Illegal))?;
+                    return Move::from_uci(&format!("{from}{rook}"), position).map_err(|err| {
+                        match err {
+                            Error::InvalidUciMove { reason, .. } => error(reason),
+                            err => err,
+                        }
+                    });
+                }
+                PieceType::Pawn
+                    if position.en_passant_square() == Some(to) && from.file() != to.file() =>
+                {
+                    MoveKind::EnPassant
+                }
+                _ if position.get_piece_at(&to).is_some() => MoveKind::Capture,
+                _ => MoveKind::Quiet,
+            },
+        };
+
+        let mv = Move::new(from, to, kind);
+        if generate_legal_moves(position)
+            .into_iter()
+            .any(|legal| legal == mv)
+        {
+            return Ok(mv);
+        }
+
+        // Tell moves the piece cannot make apart from ones that expose the king
+        let mut pseudo_legal = MoveList::new();
+        generate_moves(position, &mut pseudo_legal);
+        if pseudo_legal.into_iter().any(|candidate| candidate == mv) {
+            Err(error(UciMoveError::KingInCheck))
+        } else {
+            Err(error(UciMoveError::Illegal))
+        }
+    }
+}
+
+impl std::fmt::Debug for Move {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_null() {
+            return f.write_str("Move::NULL");
+        }
+
+        f.debug_struct("Move")
+            .field("from", &self.from_sq())
+            .field("to", &self.to_sq())
+            .field("kind", &self.kind())
+            .finish()
+    }
+}
+
+/// Subsets of the pseudo-legal moves that can be generated on their own, so a move picker can
+/// generate lazily in stages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GenType {
+    /// Every pseudo-legal move.
+    All,
+    /// Captures, en passant and every promotion, including those that do not capture.
+    Captures,
+    /// Moves that neither capture nor promote, including castling. Together with
+    /// [`GenType::Captures`] these make up [`GenType::All`].
+    Quiets,
+    /// Moves that may get the king out of check: king moves, and captures or blocks of a single
+    /// checker. Only valid when the side to move is in check.
+    Evasions,
+    /// The [`GenType::Quiets`] moves that give check, directly or by discovery.
+    QuietChecks,
+}
+
+/// Generates the pseudo-legal moves of a given type for a position
+pub fn generate(position: &Position, gen_type: GenType, moves: &mut MoveList) {
+    let us = position.side_to_move();
+    let empty = position.empty_squares();
+    let enemies = position.color_pieces(!us);
+
+    // Squares the pieces may move to quietly, capture on, and promote on by a push
+    let (quiet_targets, capture_targets, promotion_targets) = match gen_type {
+        GenType::All => (empty, enemies, empty),
+        GenType::Captures => (Bitboard::EMPTY, enemies, empty),
+        GenType::Quiets => (empty, Bitboard::EMPTY, Bitboard::EMPTY),
+        GenType::Evasions => {
+            let checkers = position.checkers();
+            debug_assert_ne!(checkers, Bitboard::EMPTY, "evasions generated out of check");
+            king_moves(position, empty | enemies, false, moves);
+
+            // Only the king can escape a double check
+            let Some(checker) = checkers.lsb().filter(|_| checkers.count() == 1) else {
+                return;
+            };
+            let blocks = between(position.king_square(us), checker);
+            (blocks, checkers, blocks)
+        }
+        GenType::QuietChecks => {
+            quiet_checks(position, moves);
+            return;
+        }
+    };
+
+    let targets = quiet_targets | capture_targets;
+    pawn_moves(
+        position,
+        quiet_targets,
+        capture_targets,
+        promotion_targets,
+        moves,
+    );
+    knight_moves(position, targets, moves);
+    bishop_moves(position, targets, moves);
+    rook_moves(position, targets, moves);
+    queen_moves(position, targets, moves);
+    if gen_type != GenType::Evasions {
+        king_moves(position, targets, gen_type != GenType::Captures, moves);
+    }
+}
+
+/// Generates the quiet moves that give check by filtering every quiet move.
+fn quiet_checks(position: &Position, moves: &mut MoveList) {
+    let mut quiets = MoveList::new();
+    generate(position, GenType::Quiets, &mut quiets);
+    for mv in quiets {
+        if position.gives_check(mv) {
+            moves.push(mv);
+        }
     }
 }
 
 /// Generates all pseudo-legal moves for a given position
 pub fn generate_moves(position: &Position, moves: &mut MoveList) {
-    pawn_moves(position, moves);
-    knight_moves(position, moves);
-    bishop_moves(position, moves);
-    rook_moves(position, moves);
-    queen_moves(position, moves);
-    king_moves(position, moves);
+    generate(position, GenType::All, moves);
+}
+
+/// Generates the pseudo-legal captures and promotions for a given position
+pub fn generate_captures(position: &Position, moves: &mut MoveList) {
+    generate(position, GenType::Captures, moves);
+}
+
+/// Generates the pseudo-legal moves that neither capture nor promote for a given position
+pub fn generate_quiets(position: &Position, moves: &mut MoveList) {
+    generate(position, GenType::Quiets, moves);
+}
+
+/// Generates the pseudo-legal check evasions for a position where the side to move is in check
+pub fn generate_evasions(position: &Position, moves: &mut MoveList) {
+    generate(position, GenType::Evasions, moves);
+}
+
+/// Generates the pseudo-legal quiet moves that give check for a given position
+pub fn generate_checks(position: &Position, moves: &mut MoveList) {
+    generate(position, GenType::QuietChecks, moves);
+}
+
+/// Generates all legal moves for a given position
+///
+/// Pseudo-legal moves are filtered using the pieces giving check and the pieces pinned to the
+/// king, so legality is decided without making any move on the board.
+pub fn generate_legal_moves(position: &Position) -> MoveList {
+    let mut pseudo_legal = MoveList::new();
+    generate_moves(position, &mut pseudo_legal);
+
+    let us = position.side_to_move();
+    let king = position.king_square(us);
+    let checkers = position.checkers();
+    let pinned = pinned_pieces(position, us);
+
+    let mut moves = MoveList::new();
+    for mv in pseudo_legal {
+        if is_legal(position, mv, king, checkers, pinned) {
+            moves.push(mv);
+        }
+    }
+
+    moves
 }
 
-fn pawn_moves(position: &Position, moves: &mut MoveList) {
+/// Returns whether a move, such as one remembered from another position, is among the
+/// pseudo-legal moves [`generate_moves`] produces for a position.
+pub fn is_pseudo_legal(position: &Position, mv: Move) -> bool {
+    let us = position.side_to_move();
+    let from = mv.from_sq();
+    let Some(piece) = position
+        .get_piece_at(&from)
+        .filter(|piece| !mv.is_null() && piece.color() == us)
+    else {
+        return false;
+    };
+
+    // Pawns and kings are generated in full, for the other pieces only the moves to the target
+    let empty = position.empty_squares();
+    let targets = empty | position.color_pieces(!us);
+    let occupied = position.occupied();
+    let mut moves = MoveList::new();
+    match piece.piece_type() {
+        PieceType::Pawn => pawn_moves(position, empty, targets & !empty, empty, &mut moves),
+        PieceType::King => king_moves(position, targets, true, &mut moves),
+        piece_type => {
+            let attacks = match piece_type {
+                PieceType::Knight => KNIGHT_ATTACK_MASKS[from.lsf_index()],
+                PieceType::Bishop => bishop_attacks(from, occupied),
+                PieceType::Rook => rook_attacks(from, occupied),
+                _ => queen_attacks(from, occupied),
+            };
+            insert_moves(
+                position,
+                from,
+                attacks & targets & mv.to_sq().into(),
+                &mut moves,
+            );
+        }
+    }
+
+    moves.into_iter().any(|candidate| candidate == mv)
+}
+
+/// Returns the pieces of a side that cannot leave the line between their king and an enemy
+/// slider without exposing the king.
+fn pinned_pieces(position: &Position, side: Color) -> Bitboard {
+    let king = position.king_square(side);
+    let them = !side;
+    let queens = position.piece(Piece::new(them, PieceType::Queen));
+    let rooks = position.piece(Piece::new(them, PieceType::Rook)) | queens;
+    let bishops = position.piece(Piece::new(them, PieceType::Bishop)) | queens;
+    let mut snipers = (rook_attacks(king, Bitboard::EMPTY) & rooks)
+        | (bishop_attacks(king, Bitboard::EMPTY) & bishops);
+
+    let occupied = position.occupied();
+    let mut pinned = Bitboard::EMPTY;
+    while let Some(sniper) = snipers.pop_lsb() {
+        let blockers = between(king, sniper) & occupied;
+        if blockers.count() == 1 {
+            pinned |= blockers & position.color_pieces(side);
+        }
+    }
+
+    pinned
+}
+
+/// Checks whether a pseudo-legal move leaves the mover's king safe.
+fn is_legal(
+    position: &Position,
+    mv: Move,
+    king: Square,
+    checkers: Bitboard,
+    pinned: Bitboard,
+) -> bool {
+    let them = !position.side_to_move();
+    let enemies = position.color_pieces(them);
+    let from = mv.from_sq();
+    let to = mv.to_sq();
+
+    if from == king {
+        // The king may not castle out of, through or into check
+        if mv.kind() == MoveKind::Castle {
+            return position.is_castling_path_safe(mv);
+        }
+
+        // Lift the king so sliders checking along the line of retreat still see through it
+        let occupied = position.occupied() ^ Bitboard::from(from);
+        return position.attackers_to(to, occupied) & enemies == Bitboard::EMPTY;
+    }
+
+    // Only the king can escape a double check
+    if checkers.count() > 1 {
+        return false;
+    }
+
+    // Removing two pawns from a rank can expose the king, so test the resulting board directly
+    if mv.kind() == MoveKind::EnPassant {
+        let captured = Square::new(to.file(), from.rank());
+        let occupied = (position.occupied() ^ Bitboard::from(from) ^ Bitboard::from(captured))
+            | Bitboard::from(to);
+        return position.attackers_to(king, occupied) & enemies & !Bitboard::from(captured)
+            == Bitboard::EMPTY;
+    }
+
+    // Any other move must capture the single checker or block its line to the king
+    if let Some(checker) = checkers.lsb()
+        && (between(king, checker) | checker.into()) & to.into() == Bitboard::EMPTY
+    {
+        return false;
+    }
+
+    pinned & from.into() == Bitboard::EMPTY || line(king, from) & to.into() != Bitboard::EMPTY
+}
+
+fn pawn_moves(
+    position: &Position,
+    quiet_targets: Bitboard,
+    capture_targets: Bitboard,
+    promotion_targets: Bitboard,
+    moves: &mut MoveList,
+) {
     let side = position.side_to_move();
     let (forward, forward_left, forward_right) = if side.is_white() {
         (Direction::North, Direction::NorthWest, Direction::NorthEast)
@@ -356,25 +863,28 @@ fn pawn_moves(position: &Position, moves: &mut MoveList) {
     let pawns = position.piece(Piece::new(side, PieceType::Pawn));
     let promotion_eligible_pawns = pawns & promotion_rank;
     let promotion_ineligible_pawns = pawns & !promotion_rank;
-    let enemy_pieces = position.color_pieces(!side);
     let empty = position.empty_squares();
 
-    // Compute pawn pushes (exclude promotions)
+    // Compute pawn pushes (exclude promotions), a blocked single push also blocks a double push
     let single_push = promotion_ineligible_pawns.shift(forward) & empty;
-    let double_push = single_push.shift(forward) & empty & double_push_rank;
+    let double_push = single_push.shift(forward) & double_push_rank & empty & quiet_targets;
 
-    insert_pawn_moves(moves, single_push, forward as i32);
+    insert_pawn_moves(moves, single_push & quiet_targets, forward as i32);
     insert_pawn_moves(moves, double_push, forward + forward);
 
     // Compute pawn captures
-    let captures_right = promotion_ineligible_pawns.shift(forward_right) & enemy_pieces;
-    let captures_left = promotion_ineligible_pawns.shift(forward_left) & enemy_pieces;
+    let captures_right = promotion_ineligible_pawns.shift(forward_right) & capture_targets;
+    let captures_left = promotion_ineligible_pawns.shift(forward_left) & capture_targets;
     insert_pawn_captures(moves, captures_left, forward_left as i32);
     insert_pawn_captures(moves, captures_right, forward_right as i32);
 
-    if position.has_en_passant() {
-        let mut en_passant_capture_right = promotion_ineligible_pawns.shift(forward_right)
-            & position.en_passant_square().unwrap().into();
+    // En passant captures the pawn that just moved and can never block a check, so it is
+    // generated whenever that pawn is a capture target
+    if let Some(ep_target) = position.en_passant_square()
+        && capture_targets & Bitboard::from(ep_target).shift(forward.flip()) != Bitboard::EMPTY
+    {
+        let mut en_passant_capture_right =
+            promotion_ineligible_pawns.shift(forward_right) & Bitboard::from(ep_target);
         while let Some(to) = en_passant_capture_right.pop_lsb() {
             let from = Bitboard::from(to)
                 .shift(forward_right.flip())
@@ -383,8 +893,8 @@ fn pawn_moves(position: &Position, moves: &mut MoveList) {
             moves.push(Move::new(from, to, MoveKind::EnPassant));
         }
 
-        let mut en_passant_capture_left = promotion_ineligible_pawns.shift(forward_left)
-            & position.en_passant_square().unwrap().into();
+        let mut en_passant_capture_left =
+            promotion_ineligible_pawns.shift(forward_left) & Bitboard::from(ep_target);
         while let Some(to) = en_passant_capture_left.pop_lsb() {
             let from = Bitboard::from(to)
                 .shift(forward_left.flip())
@@ -395,9 +905,9 @@ fn pawn_moves(position: &Position, moves: &mut MoveList) {
     }
 
     // Compute promotions
-    let push_promotions = promotion_eligible_pawns.shift(forward) & empty;
-    let capture_promo_right = promotion_eligible_pawns.shift(forward_right) & enemy_pieces;
-    let capture_promo_left = promotion_eligible_pawns.shift(forward_left) & enemy_pieces;
+    let push_promotions = promotion_eligible_pawns.shift(forward) & empty & promotion_targets;
+    let capture_promo_right = promotion_eligible_pawns.shift(forward_right) & capture_targets;
+    let capture_promo_left = promotion_eligible_pawns.shift(forward_left) & capture_targets;
 
     insert_promotion(moves, push_promotions, forward);
     insert_promotion(moves, capture_promo_right, forward_right);
@@ -445,125 +955,86 @@ fn make_promotion(moves: &mut MoveList, source: Square, dest: Square) {
     ));
 }
 
-fn knight_moves(position: &Position, moves: &mut MoveList) {
-    let blockers = position.color_pieces(position.side_to_move());
+fn knight_moves(position: &Position, targets: Bitboard, moves: &mut MoveList) {
     let mut knights = position.piece(Piece::new(position.side_to_move(), PieceType::Knight));
     while let Some(source) = knights.pop_lsb() {
-        let hops = KNIGHT_ATTACK_MASKS[source.lsf_index()] & !blockers;
+        let hops = KNIGHT_ATTACK_MASKS[source.lsf_index()] & targets;
         insert_moves(position, source, hops, moves);
     }
 }
 
-fn bishop_moves(position: &Position, moves: &mut MoveList) {
+fn bishop_moves(position: &Position, targets: Bitboard, moves: &mut MoveList) {
     let to_move = position.side_to_move();
     let occupied = position.occupied();
-    let friendly = position.color_pieces(to_move);
     let mut bishops = position.piece(Piece::new(to_move, PieceType::Bishop));
 
     while let Some(source) = bishops.pop_lsb() {
-        let bishop_moves = bishop_attacks(source, occupied) & !friendly;
+        let bishop_moves = bishop_attacks(source, occupied) & targets;
         insert_moves(position, source, bishop_moves, moves);
     }
 }
 
-fn rook_moves(position: &Position, moves: &mut MoveList) {
+fn rook_moves(position: &Position, targets: Bitboard, moves: &mut MoveList) {
     let to_move = position.side_to_move();
     let occupied = position.occupied();
-    let friendly = position.color_pieces(to_move);
     let mut rooks = position.piece(Piece::new(to_move, PieceType::Rook));
 
     while let Some(source) = rooks.pop_lsb() {
-        let rook_moves = rook_attacks(source, occupied) & !friendly;
+        let rook_moves = rook_attacks(source, occupied) & targets;
         insert_moves(position, source, rook_moves, moves);
     }
 }
 
-fn queen_moves(position: &Position, moves: &mut MoveList) {
+fn queen_moves(position: &Position, targets: Bitboard, moves: &mut MoveList) {
     let to_move = position.side_to_move();
     let occupied = position.occupied();
-    let friendly = position.color_pieces(to_move);
     let mut queens = position.piece(Piece::new(to_move, PieceType::Queen));
 
     while let Some(source) = queens.pop_lsb() {
-        let queen_moves = queen_attacks(source, occupied) & !friendly;
+        let queen_moves = queen_attacks(source, occupied) & targets;
         insert_moves(position, source, queen_moves, moves);
     }
 }
 
-fn king_moves(position: &Position, moves: &mut MoveList) {
+fn king_moves(position: &Position, targets: Bitboard, castling: bool, moves: &mut MoveList) {
     let to_move = position.side_to_move();
-    let castle_mask_shift_value = if to_move.is_white() { 0 } else { 56 };
-    let queen_castle_mask = Bitboard(0xe << castle_mask_shift_value);
-    let king_castle_mask = Bitboard(0x60 << castle_mask_shift_value);
-    let castle_mask = king_castle_mask | queen_castle_mask;
     let king = position.piece(Piece::new(to_move, PieceType::King));
     let king_square = king.lsb().unwrap();
-    let free = position.empty_squares();
-    let mut captures_bb =
-        KING_ATTACK_MASKS[king_square.lsf_index()] & position.color_pieces(!to_move);
-    let mut quiet_bb = KING_ATTACK_MASKS[king_square.lsf_index()] & free;
-    let castle_bb = castle_mask & free;
-
-    while let Some(capture_square) = captures_bb.pop_lsb() {
-        moves.push(Move::new(king_square, capture_square, MoveKind::Capture));
+    let occupied = position.occupied();
+    let destinations = KING_ATTACK_MASKS[king_square.lsf_index()] & targets;
+    insert_moves(position, king_square, destinations, moves);
+    if !castling {
+        return;
     }
 
-    while let Some(move_square) = quiet_bb.pop_lsb() {
-        moves.push(Move::new(king_square, move_square, MoveKind::Quiet));
-    }
+    for side in [CastlingRights::KingSide, CastlingRights::QueenSide] {
+        let Some(rook_square) = position.castling_rook(to_move, side) else {
+            continue;
+        };
 
-    match position.castling_rights(to_move) {
-        CastlingRights::All => {
-            generate_king_castle(king_square, castle_bb, king_castle_mask, moves);
-            generate_queen_castle(king_square, castle_bb, queen_castle_mask, moves);
-        }
-        CastlingRights::KingSide => {
-            generate_king_castle(king_square, castle_bb, king_castle_mask, moves);
-        }
-        CastlingRights::QueenSide => {
-            generate_queen_castle(king_square, castle_bb, queen_castle_mask, moves);
+        // Every square either piece crosses or lands on must be empty, bar the two of them
+        let castle = Move::new(king_square, rook_square, MoveKind::Castle);
+        let (king_destination, rook_destination) = castle.castling_squares();
+        let path = between(king_square, king_destination)
+            | king_destination.into()
+            | between(rook_square, rook_destination)
+            | rook_destination.into();
+        let blockers = occupied ^ Bitboard::from(king_square) ^ rook_square.into();
+        if path & blockers == Bitboard::EMPTY {
+            moves.push(castle);
         }
-        _ => (),
-    }
-}
-
-fn insert_moves(
-    position: &Position,
-    source: Square,
-    mut locations: Bitboard,
-    moves: &mut MoveList,
-) {
-    while let Some(dest) = locations.pop_lsb() {
-        let dest_occupancy_opt = position.get_piece_at(&dest);
-        if dest_occupancy_opt.is_some_and(|p| p.color() != position.side_to_move()) {
-            moves.push(Move::new(source, dest, MoveKind::Capture));
-        } else if dest_occupancy_opt.is_none() {
-            moves.push(Move::new(source, dest, MoveKind::Quiet));
-        };
     }
 }
 
-fn generate_queen_castle(
-    king_square: Square,
-    open_castle_bb: Bitboard,
-    castle_mask: Bitboard,
-    moves: &mut MoveList,
-) {
-    if open_castle_bb & castle_mask == castle_mask {
-        let castle_square = Square::new(king_square.file().left_n(2).unwrap(), king_square.rank());
-        moves.push(Move::new(king_square, castle_square, MoveKind::Castle));
+fn insert_moves(position: &Position, source: Square, locations: Bitboard, moves: &mut MoveList) {
+    let mut captures = locations & position.color_pieces(!position.side_to_move());
+    let mut quiets = locations & position.empty_squares();
+    while let Some(dest) = captures.pop_lsb() {
+        moves.push(Move::new(source, dest, MoveKind::Capture));
     }
-}
 
-fn generate_king_castle(
-    king_square: Square,
-    open_castle_bb: Bitboard,
-    castle_mask: Bitboard,
-    moves: &mut MoveList,
-) {
-    if open_castle_bb & castle_mask == castle_mask {
-        let castle_square = Square::new(king_square.file().right_n(2).unwrap(), king_square.rank());
-        moves.push(Move::new(king_square, castle_square, MoveKind::Castle));
+    while let Some(dest) = quiets.pop_lsb() {
+        moves.push(Move::new(source, dest, MoveKind::Quiet));
     }
 }
 
@@ -572,37 +1043,11 @@ pub(crate) fn queen_attacks(square: Square, occupied: Bitboard) -> Bitboard {
 }
 
 pub(crate) fn rook_attacks(square: Square, occupied: Bitboard) -> Bitboard {
-    let file_attacks = get_ray_attacks(square, Direction::North, occupied)
-        | get_ray_attacks(square, Direction::South, occupied);
-    let rank_attacks = get_ray_attacks(square, Direction::East, occupied)
-        | get_ray_attacks(square, Direction::West, occupied);
-
-    file_attacks | rank_attacks
+    magic::rook_attacks(square, occupied)
 }
 
 pub(crate) fn bishop_attacks(square: Square, occupied: Bitboard) -> Bitboard {
-    let diagonal = get_ray_attacks(square, Direction::NorthEast, occupied)
-        | get_ray_attacks(square, Direction::SouthWest, occupied);
-    let anti_diagonal = get_ray_attacks(square, Direction::NorthWest, occupied)
-        | get_ray_attacks(square, Direction::SouthEast, occupied);
-
-    diagonal | anti_diagonal
-}
-
-fn get_ray_attacks(square: Square, dir: Direction, occupied: Bitboard) -> Bitboard {
-    let mut attacks = SLIDING_ATTACK_MASKS[dir.mask_cache_index()][square.lsf_index()];
-    let blockers = attacks & occupied;
-    let blocker = if dir.is_negative() {
-        blockers.msb()
-    } else {
-        blockers.lsb()
-    };
-
-    if let Some(block_square) = blocker {
-        attacks ^= SLIDING_ATTACK_MASKS[dir.mask_cache_index()][block_square.lsf_index()];
-    }
-
-    attacks
+    magic::bishop_attacks(square, occupied)
 }
 
 #[cfg(test)]
@@ -621,130 +1066,369 @@ mod tests {
     const POSITION_6: &str =
         "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10 ";
 
+    fn legal_uci_moves(fen: &str) -> Vec<String> {
+        let position = Position::from_str(fen).unwrap();
+        let mut moves: Vec<String> = generate_legal_moves(&position)
+            .into_iter()
+            .map(|mv| mv.to_uci_string())
+            .collect();
+        moves.sort();
+        moves
+    }
+
+    #[test]
+    fn test_move_packing() {
+        assert_eq!(2, std::mem::size_of::<Move>());
+        for (from, to, kind) in [
+            (Square::E2, Square::E4, MoveKind::Quiet),
+            (Square::D4, Square::E5, MoveKind::Capture),
+            (Square::E5, Square::D6, MoveKind::EnPassant),
+            (Square::E8, Square::A8, MoveKind::Castle),
+            (
+                Square::B7,
+                Square::A8,
+                MoveKind::Promotion(PieceType::Knight),
+            ),
+            (
+                Square::G2,
+                Square::G1,
+                MoveKind::Promotion(PieceType::Bishop),
+            ),
+            (Square::A7, Square::A8, MoveKind::Promotion(PieceType::Rook)),
+            (
+                Square::H2,
+                Square::H1,
+                MoveKind::Promotion(PieceType::Queen),
+            ),
+            (Square::H8, Square::A1, MoveKind::Quiet),
+        ] {
+            let mv = Move::new(from, to, kind);
+            assert_eq!(from, mv.from_sq());
+            assert_eq!(to, mv.to_sq());
+            assert_eq!(kind, mv.kind());
+            assert_eq!(mv, Move::from_bits(mv.to_bits()));
+            assert!(!mv.is_null());
+        }
+
+        assert!(Move::NULL.is_null());
+        assert!(MoveList::new().into_iter().next().is_none());
+    }
+
     #[test]
-    #[cfg_attr(debug_assertions, ignore = "only enabled for '--release'")]
-    fn test_start_position_perft() {
-        let result = do_perft(START_POSITION, 6, 119060324);
+    fn test_staged_generation() {
+        for fen in [
+            START_POSITION,
+            KIWIPETE,
+            POSITION_3,
+            POSITION_4,
+            POSITION_5,
+            POSITION_6,
+            "5k2/8/8/8/8/8/8/4K2R w K - 0 1",
+            "1rqbkrbn/1ppppp1p/1n6/p1N3p1/8/2P4P/PP1PPPP1/1RQBKRBN w FBfb - 0 9",
+        ] {
+            let mut position = Position::from_str(fen).unwrap();
+            verify_stages(&mut position, &mut Vec::new(), 2);
+        }
+    }
 
-        assert!(result.is_ok());
+    /// Checks that captures and quiets split the pseudo-legal moves, that the evasions keep
+    /// every legal move when in check, and that the quiet checks are exactly the quiets giving
+    /// check, for every position reachable within `depth` plies
+    fn verify_stages(position: &mut Position, history: &mut Vec<State>, depth: u32) {
+        let generated = |gen_type| {
+            let mut moves = MoveList::new();
+            generate(position, gen_type, &mut moves);
+            moves.into_iter().collect::<Vec<_>>()
+        };
+        let fen = position.to_fen();
+        let all = generated(GenType::All);
+        let captures = generated(GenType::Captures);
+        let quiets = generated(GenType::Quiets);
+        let mut staged: Vec<_> = captures.iter().chain(&quiets).copied().collect();
+        assert_eq!(all.len(), staged.len(), "{fen}");
+        staged.retain(|mv| all.contains(mv));
+        assert_eq!(all.len(), staged.len(), "{fen}");
+        assert!(
+            quiets
+                .iter()
+                .all(|mv| matches!(mv.kind(), MoveKind::Quiet | MoveKind::Castle)),
+            "{fen}"
+        );
+
+        let legal: Vec<_> = generate_legal_moves(position).into_iter().collect();
+        if position.is_checked(position.side_to_move()) {
+            let evasions = generated(GenType::Evasions);
+            assert!(evasions.iter().all(|mv| all.contains(mv)), "{fen}");
+            assert!(legal.iter().all(|mv| evasions.contains(mv)), "{fen}");
+        }
+
+        let checks = generated(GenType::QuietChecks);
+        for mv in legal {
+            position.make_move(mv, history);
+            let gives_check = position.is_checked(position.side_to_move());
+            if depth > 1 {
+                verify_stages(position, history, depth - 1);
+            }
+            position.unmake_move(mv, history);
+
+            assert_eq!(gives_check, position.gives_check(mv), "{fen} {mv:?}");
+            assert_eq!(
+                gives_check && quiets.contains(&mv),
+                checks.contains(&mv),
+                "{fen} {mv:?}"
+            );
+        }
     }
 
     #[test]
-    #[cfg_attr(debug_assertions, ignore = "only enabled for '--release'")]
-    fn test_kiwipete_perft() {
-        let result = do_perft(KIWIPETE, 5, 193690690);
+    fn test_is_pseudo_legal() {
+        let positions: Vec<Position> = [
+            START_POSITION,
+            KIWIPETE,
+            POSITION_3,
+            POSITION_4,
+            POSITION_5,
+            POSITION_6,
+            "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1",
+        ]
+        .iter()
+        .map(|fen| Position::from_str(fen).unwrap())
+        .collect();
+        let generated = |position: &Position| {
+            let mut moves = MoveList::new();
+            generate_moves(position, &mut moves);
+            moves.into_iter().collect::<Vec<_>>()
+        };
+
+        // Every move of every position, along with the same squares under every other kind
+        let mut candidates = vec![Move::NULL];
+        for mv in positions.iter().flat_map(generated) {
+            for kind in [
+                MoveKind::Quiet,
+                MoveKind::Capture,
+                MoveKind::EnPassant,
+                MoveKind::Castle,
+                MoveKind::Promotion(PieceType::Knight),
+                MoveKind::Promotion(PieceType::Queen),
+            ] {
+                candidates.push(Move::new(mv.from_sq(), mv.to_sq(), kind));
+            }
+        }
 
-        assert!(result.is_ok());
+        for position in &positions {
+            let moves = generated(position);
+            for &mv in &candidates {
+                assert_eq!(
+                    moves.contains(&mv),
+                    is_pseudo_legal(position, mv),
+                    "{} {mv:?}",
+                    position.to_fen()
+                );
+            }
+        }
     }
 
     #[test]
-    #[cfg_attr(debug_assertions, ignore = "only enabled for '--release'")]
-    fn test_position_3_perft() {
-        let result = do_perft(POSITION_3, 7, 178633661);
+    fn test_legal_moves_respect_pins() {
+        let moves = legal_uci_moves("4k3/4r3/8/8/1b6/8/3NR3/4K3 w - - 0 1");
 
-        assert!(result.is_ok());
+        assert!(moves.iter().all(|mv| !mv.starts_with("d2")));
+        assert!(moves.contains(&"e2e7".to_string()));
+        assert!(!moves.contains(&"e2d2".to_string()));
     }
 
     #[test]
-    #[cfg_attr(debug_assertions, ignore = "only enabled for '--release'")]
-    fn test_position_4_perft() {
-        let result = do_perft(POSITION_4, 5, 15833292);
+    fn test_legal_moves_evade_check() {
+        let moves = legal_uci_moves("4k3/8/8/8/8/5n2/4B3/4K2R w K - 0 1");
 
-        assert!(result.is_ok());
+        assert_eq!(vec!["e1d1", "e1f1", "e1f2", "e2f3"], moves);
     }
 
     #[test]
-    #[cfg_attr(debug_assertions, ignore = "only enabled for '--release'")]
-    fn test_position_5_perft() {
-        let result = do_perft(POSITION_5, 5, 89941194);
+    fn test_legal_moves_double_check() {
+        let moves = legal_uci_moves("4k3/8/8/8/1b6/3n4/8/R3K2R w KQ - 0 1");
 
-        assert!(result.is_ok());
+        assert_eq!(vec!["e1d1", "e1e2", "e1f1"], moves);
     }
 
     #[test]
-    #[cfg_attr(debug_assertions, ignore = "only enabled for '--release'")]
-    fn test_position_6_perft() {
-        let result = do_perft(POSITION_6, 5, 164075551);
-
-        assert!(result.is_ok());
-    }
-
-    /// Generates all legal moves for a given position
-    fn generate_legal_moves(position: &mut Position, moves: &mut MoveList) {
-        let mut curr = moves.len();
-        generate_moves(position, moves);
-
-        let mut history = vec![];
-        while curr < moves.len() {
-            let mv = moves.get(curr).expect("move does not exist");
-            if !position.make_move(mv, &mut history) {
-                moves.swap_remove(curr);
-            } else {
-                curr += 1;
-                position.unmake_move(mv, &mut history);
-            }
-        }
+    fn test_legal_moves_castling_through_check() {
+        let moves = legal_uci_moves("4k3/8/8/8/8/8/5r2/R3K2R w KQ - 0 1");
+
+        assert!(moves.contains(&"e1c1".to_string()));
+        assert!(!moves.contains(&"e1g1".to_string()));
     }
 
-    /// Perft testing function
-    fn perft(pos: &mut Position, history: &mut Vec<State>, depth: u64) -> u64 {
-        if depth == 0 {
-            return 1;
-        }
+    #[test]
+    fn test_legal_moves_en_passant_discovered_check() {
+        let moves = legal_uci_moves("8/8/8/KPp4r/8/8/8/4k3 w - c6 0 1");
+
+        assert!(!moves.contains(&"b5c6".to_string()));
+        assert!(moves.contains(&"b5b6".to_string()));
+    }
 
-        let mut moves = MoveList::new();
-        generate_legal_moves(pos, &mut moves);
+    fn uci_error(uci: &str, fen: &str) -> UciMoveError {
+        match Move::from_uci(uci, &Position::from_str(fen).unwrap()) {
+            Err(Error::InvalidUciMove { reason, .. }) => reason,
+            result => panic!("expected UCI move error for '{uci}', got {result:?}"),
+        }
+    }
 
-        if depth == 1 {
-            return moves.len() as u64;
+    #[test]
+    fn test_from_uci() {
+        let p = Position::from_str("r3k2r/1P4pp/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1").unwrap();
+
+        for (uci, from, to, kind) in [
+            ("a1b1", Square::A1, Square::B1, MoveKind::Quiet),
+            ("a1a8", Square::A1, Square::A8, MoveKind::Capture),
+            ("e5d6", Square::E5, Square::D6, MoveKind::EnPassant),
+            ("e1g1", Square::E1, Square::H1, MoveKind::Castle),
+            ("e1c1", Square::E1, Square::A1, MoveKind::Castle),
+            (
+                "b7b8q",
+                Square::B7,
+                Square::B8,
+                MoveKind::Promotion(PieceType::Queen),
+            ),
+            (
+                "b7a8n",
+                Square::B7,
+                Square::A8,
+                MoveKind::Promotion(PieceType::Knight),
+            ),
+        ] {
+            assert_eq!(
+                Move::new(from, to, kind),
+                Move::from_uci(uci, &p).unwrap(),
+                "{uci}"
+            );
         }
 
-        let mut move_count = 0;
-        for mv in moves {
-            if !pos.make_move(mv, history) {
-                panic!("legal move couldn't be made");
-            }
-            move_count += perft(pos, history, depth - 1);
-            pos.unmake_move(mv, history);
+        for mv in generate_legal_moves(&p) {
+            assert_eq!(mv, Move::from_uci(&mv.to_uci_string(), &p).unwrap());
         }
+    }
 
-        move_count
+    #[test]
+    fn test_from_uci_errors() {
+        for (uci, fen, expected) in [
+            ("e2", STARTING_FEN, UciMoveError::Syntax),
+            ("e2e4qq", STARTING_FEN, UciMoveError::Syntax),
+            ("e2i4", STARTING_FEN, UciMoveError::Syntax),
+            ("e7e8k", STARTING_FEN, UciMoveError::Syntax),
+            ("e3e4", STARTING_FEN, UciMoveError::EmptySquare(Square::E3)),
+            ("e7e5", STARTING_FEN, UciMoveError::WrongColor(Square::E7)),
+            ("e2e5", STARTING_FEN, UciMoveError::Illegal),
+            ("e1g1", STARTING_FEN, UciMoveError::Illegal),
+            ("e2e4q", STARTING_FEN, UciMoveError::UnexpectedPromotion),
+            (
+                "b7b8",
+                "4k3/1P6/8/8/8/8/8/4K3 w - - 0 1",
+                UciMoveError::MissingPromotion,
+            ),
+            (
+                "e2d3",
+                "4k3/4r3/8/8/8/3p4/4B3/4K3 w - - 0 1",
+                UciMoveError::KingInCheck,
+            ),
+        ] {
+            assert_eq!(expected, uci_error(uci, fen), "{uci}");
+        }
     }
 
-    fn perft_divide(pos: &mut Position, history: &mut Vec<State>, depth: u64) -> u64 {
-        if depth == 0 {
-            return 1;
+    #[test]
+    fn test_chess960_castling() {
+        for (fen, uci, after) in [
+            // The king already stands on its destination
+            (
+                "4k3/8/8/8/8/8/8/6KR w K - 0 1",
+                "g1h1",
+                "4k3/8/8/8/8/8/8/5RK1 b - - 1 1",
+            ),
+            // King and rook swap squares
+            (
+                "4k3/8/8/8/8/8/8/5KR1 w K - 0 1",
+                "f1g1",
+                "4k3/8/8/8/8/8/8/5RK1 b - - 1 1",
+            ),
+            (
+                "4k3/8/8/8/8/8/8/1R3K2 w Q - 0 1",
+                "f1b1",
+                "4k3/8/8/8/8/8/8/2KR4 b - - 1 1",
+            ),
+        ] {
+            let mut p = Position::from_str(fen).unwrap();
+            let mv = Move::from_uci(uci, &p).unwrap();
+            let mut history = Vec::new();
+
+            assert_eq!(MoveKind::Castle, mv.kind(), "{fen}");
+            assert!(
+                generate_legal_moves(&p)
+                    .into_iter()
+                    .any(|legal| legal == mv)
+            );
+            assert!(p.make_move(mv, &mut history));
+            assert_eq!(after, p.to_fen());
+            p.unmake_move(mv, &mut history);
+            assert_eq!(fen, p.to_fen());
         }
+    }
 
-        let mut moves = MoveList::new();
-        generate_legal_moves(pos, &mut moves);
+    #[test]
+    fn test_chess960_castling_rook_shields_destination() {
+        // The castling rook blocks the a1 rook, which would attack the king on c1
+        let mut p = Position::from_str("4k3/8/8/8/8/8/8/rR3K2 w Q - 0 1").unwrap();
+        let castle = Move::new(Square::F1, Square::B1, MoveKind::Castle);
 
-        let mut move_count = 0;
-        for mv in moves {
-            if !pos.make_move(mv, history) {
-                panic!("legal move couldn't be made");
-            }
-            let subtree_count = if depth == 1 {
-                1
-            } else {
-                perft(pos, history, depth - 1)
-            };
-            move_count += subtree_count;
-            pos.unmake_move(mv, history);
-            println!("{}: {}", mv.to_uci_string(), subtree_count);
-        }
+        assert!(!generate_legal_moves(&p).into_iter().any(|mv| mv == castle));
+        assert!(!p.make_move(castle, &mut Vec::new()));
+    }
 
-        println!("\nNodes searched: {}", move_count);
-        move_count
+    #[test]
+    fn test_castling_uci_notation() {
+        let p = Position::from_str("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        let castle = Move::new(Square::E1, Square::H1, MoveKind::Castle);
+
+        assert_eq!("e1g1", castle.to_uci_string());
+        assert_eq!("e1h1", castle.to_uci(true));
+        assert_eq!(castle, Move::from_uci("e1g1", &p).unwrap());
+        assert_eq!(castle, Move::from_uci("e1h1", &p).unwrap());
+
+        let mut chess960 = p.clone();
+        chess960.set_chess960(true);
+        assert_eq!(castle, Move::from_uci("e1h1", &chess960).unwrap());
+        assert!(Move::from_uci("e1g1", &chess960).is_err());
     }
 
-    fn do_perft(pos: &str, starting_depth: u64, expected_count: u64) -> Result<(), Error> {
-        let mut position = Position::from_str(pos)?;
-        let mut history = Vec::new();
-        let legal_count = perft_divide(&mut position, &mut history, starting_depth);
+    #[test]
+    fn test_fen_round_trip() {
+        let depth = if cfg!(debug_assertions) { 2 } else { 3 };
+        for fen in [
+            START_POSITION,
+            KIWIPETE,
+            POSITION_3,
+            POSITION_4,
+            POSITION_5,
+            POSITION_6,
+        ] {
+            let mut position = Position::from_str(fen).unwrap();
+            fen_round_trip(&mut position, &mut Vec::new(), depth);
+        }
+    }
 
-        assert_eq!(expected_count, legal_count);
+    /// Checks that every position reachable within `depth` plies survives a FEN round trip
+    fn fen_round_trip(pos: &mut Position, history: &mut Vec<State>, depth: u64) {
+        let fen = pos.to_fen();
+        assert_eq!(*pos, Position::from_str(&fen).unwrap(), "{fen}");
+        if depth == 0 {
+            return;
+        }
 
-        Ok(())
+        let moves = generate_legal_moves(pos);
+        for mv in moves {
+            pos.make_move(mv, history);
+            fen_round_trip(pos, history, depth - 1);
+            pos.unmake_move(mv, history);
+        }
     }
 }
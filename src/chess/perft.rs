@@ -0,0 +1,244 @@
+use std::str::FromStr;
+
+use crate::chess::{
+    Error, Position, State,
+    movegen::{Move, generate_legal_moves},
+};
+
+/// Counts the leaf nodes of the legal move tree rooted at `position`, `depth` plies deep.
+///
+/// See <https://www.chessprogramming.org/Perft> for reference counts.
+pub fn perft(position: &Position, depth: u32) -> u64 {
+    count_nodes(&mut position.clone(), &mut Vec::new(), depth)
+}
+
+/// Splits the perft count of `position` by root move, in generation order.
+///
+/// Comparing this against another engine's output narrows a mismatch down to a single move.
+pub fn divide(position: &Position, depth: u32) -> Vec<(Move, u64)> {
+    if depth == 0 {
+        return Vec::new();
+    }
+
+    let mut position = position.clone();
+    let mut history = Vec::new();
+
+    generate_legal_moves(&position)
+        .into_iter()
+        .map(|mv| {
+            position.make_move(mv, &mut history);
+            let nodes = count_nodes(&mut position, &mut history, depth - 1);
+            position.unmake_move(mv, &mut history);
+            (mv, nodes)
+        })
+        .collect()
+}
+
+fn count_nodes(position: &mut Position, history: &mut Vec<State>, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+
+    let moves = generate_legal_moves(position);
+
+    // Legal moves at the last ply are leaves, so there is no need to make them
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+
+    let mut nodes = 0;
+    for mv in moves {
+        position.make_move(mv, history);
+        nodes += count_nodes(position, history, depth - 1);
+        position.unmake_move(mv, history);
+    }
+
+    nodes
+}
+
+/// A line of a perft suite, in the EPD-style `<fen> ;D1 20 ;D2 400 ...` format.
+///
+/// The FEN may omit the halfmove clock and fullmove number, as EPD records do.
+#[derive(Debug, Clone)]
+pub struct PerftEntry {
+    pub fen: String,
+    pub position: Position,
+    /// Expected node counts as `(depth, nodes)` pairs, in the order listed.
+    pub expected: Vec<(u32, u64)>,
+}
+
+impl FromStr for PerftEntry {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::InvalidPerftEntry(s.to_string());
+        let mut fields = s.split(';');
+        let fen = fields.next().ok_or_else(invalid)?.trim();
+
+        // EPD records only carry the first four FEN fields
+        let fen = if fen.split_whitespace().count() == 4 {
+            format!("{fen} 0 1")
+        } else {
+            fen.to_string()
+        };
+        let position = fen.parse()?;
+
+        let expected = fields
+            .map(|field| {
+                let (depth, nodes) = field.trim().split_once(char::is_whitespace)?;
+                let depth = depth.strip_prefix('D')?.parse().ok()?;
+                let nodes = nodes.trim().parse().ok()?;
+                Some((depth, nodes))
+            })
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(invalid)?;
+        if expected.is_empty() {
+            return Err(invalid());
+        }
+
+        Ok(Self {
+            fen,
+            position,
+            expected,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chess::STARTING_FEN;
+
+    use super::*;
+
+    const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+    const POSITION_3: &str = "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1";
+    const POSITION_4: &str = "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1";
+    const POSITION_5: &str = "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8";
+    const POSITION_6: &str =
+        "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10 ";
+
+    fn assert_perft(fen: &str, depth: u32, expected: u64) {
+        let position = Position::from_str(fen).unwrap();
+
+        assert_eq!(expected, perft(&position, depth));
+    }
+
+    #[test]
+    #[cfg_attr(debug_assertions, ignore = "only enabled for '--release'")]
+    fn test_start_position_perft() {
+        assert_perft(STARTING_FEN, 6, 119060324);
+    }
+
+    #[test]
+    #[cfg_attr(debug_assertions, ignore = "only enabled for '--release'")]
+    fn test_kiwipete_perft() {
+        assert_perft(KIWIPETE, 5, 193690690);
+    }
+
+    #[test]
+    #[cfg_attr(debug_assertions, ignore = "only enabled for '--release'")]
+    fn test_position_3_perft() {
+        assert_perft(POSITION_3, 7, 178633661);
+    }
+
+    #[test]
+    #[cfg_attr(debug_assertions, ignore = "only enabled for '--release'")]
+    fn test_position_4_perft() {
+        assert_perft(POSITION_4, 5, 15833292);
+    }
+
+    #[test]
+    #[cfg_attr(debug_assertions, ignore = "only enabled for '--release'")]
+    fn test_position_5_perft() {
+        assert_perft(POSITION_5, 5, 89941194);
+    }
+
+    #[test]
+    #[cfg_attr(debug_assertions, ignore = "only enabled for '--release'")]
+    fn test_position_6_perft() {
+        assert_perft(POSITION_6, 5, 164075551);
+    }
+
+    #[test]
+    #[cfg_attr(debug_assertions, ignore = "only enabled for '--release'")]
+    fn test_chess960_perft() {
+        for (fen, expected) in [
+            (
+                "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+                8146062,
+            ),
+            (
+                "2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9",
+                16253601,
+            ),
+            (
+                "b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRKRB w GE - 1 9",
+                6417013,
+            ),
+            (
+                "qbbnnrkr/2pp2pp/p7/1p2pp2/8/P3PP2/1PPP1KPP/QBBNNR1R w hf - 0 9",
+                9183776,
+            ),
+            (
+                "1nbbnrkr/p1p1ppp1/3p4/1p3P1p/3Pq2P/8/PPP1P1P1/QNBBNRKR w HFhf - 0 9",
+                34030312,
+            ),
+        ] {
+            assert_perft(fen, 5, expected);
+        }
+    }
+
+    #[test]
+    fn test_shallow_perft() {
+        assert_perft(STARTING_FEN, 0, 1);
+        assert_perft(STARTING_FEN, 3, 8902);
+        assert_perft(KIWIPETE, 2, 2039);
+    }
+
+    #[test]
+    fn test_divide() {
+        let position = Position::from_str(KIWIPETE).unwrap();
+        let divide = divide(&position, 2);
+
+        assert_eq!(48, divide.len());
+        assert_eq!(2039, divide.iter().map(|&(_, nodes)| nodes).sum::<u64>());
+        assert!(
+            divide
+                .iter()
+                .any(|&(mv, nodes)| mv.to_uci_string() == "e1g1" && nodes == 43)
+        );
+    }
+
+    #[test]
+    fn test_parse_perft_entry() {
+        let entry: PerftEntry = "4k3/8/8/8/8/8/8/4K2R w K - ;D1 15 ;D2 66 ;D3 1197"
+            .parse()
+            .unwrap();
+
+        assert_eq!("4k3/8/8/8/8/8/8/4K2R w K - 0 1", entry.fen);
+        assert_eq!(vec![(1, 15), (2, 66), (3, 1197)], entry.expected);
+        for (depth, nodes) in entry.expected {
+            assert_eq!(nodes, perft(&entry.position, depth));
+        }
+    }
+
+    #[test]
+    fn test_parse_invalid_perft_entry() {
+        for line in [
+            "4k3/8/8/8/8/8/8/4K2R w K - 0 1",
+            "4k3/8/8/8/8/8/8/4K2R w K - 0 1 ;D1",
+            "4k3/8/8/8/8/8/8/4K2R w K - 0 1 ;X1 15",
+            "4k3/8/8/8/8/8/8/4K2R w K - 0 1 ;D1 many",
+        ] {
+            assert!(
+                matches!(line.parse::<PerftEntry>(), Err(Error::InvalidPerftEntry(_))),
+                "{line}"
+            );
+        }
+
+        assert!(matches!(
+            "4k3/8/8/8/8/8/8/4K2X w K - ;D1 15".parse::<PerftEntry>(),
+            Err(Error::InvalidFen { .. })
+        ));
+    }
+}
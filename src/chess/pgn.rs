@@ -0,0 +1,759 @@
+use std::{
+    fmt::{self, Display},
+    io::BufRead,
+    mem,
+    str::FromStr,
+};
+
+use crate::chess::{Color, Error, PgnError, Position, movegen::Move};
+
+/// Column at which written movetext is wrapped, as the PGN export format recommends.
+const LINE_WIDTH: usize = 80;
+
+/// The result token that ends the movetext of a PGN game.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+    /// The game is ongoing, abandoned or its result is not known, written `*`.
+    #[default]
+    Unknown,
+}
+
+impl Display for GameResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let token = match self {
+            GameResult::WhiteWins => "1-0",
+            GameResult::BlackWins => "0-1",
+            GameResult::Draw => "1/2-1/2",
+            GameResult::Unknown => "*",
+        };
+
+        write!(f, "{token}")
+    }
+}
+
+impl FromStr for GameResult {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "1-0" => Ok(GameResult::WhiteWins),
+            "0-1" => Ok(GameResult::BlackWins),
+            "1/2-1/2" => Ok(GameResult::Draw),
+            "*" => Ok(GameResult::Unknown),
+            _ => Err(Error::ParseError),
+        }
+    }
+}
+
+/// A move of PGN movetext together with its annotations and alternatives.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PgnMove {
+    pub mv: Move,
+    /// Numeric annotation glyphs, e.g. 1 for `!` and 4 for `??`.
+    pub nags: Vec<u8>,
+    /// Comments before the move that no earlier move of its line can own, e.g. at the start of a
+    /// variation.
+    pub comments_before: Vec<String>,
+    /// Comments after the move.
+    pub comments: Vec<String>,
+    /// Alternatives to this move, each played from the position before it.
+    pub variations: Vec<Vec<PgnMove>>,
+}
+
+impl PgnMove {
+    pub fn new(mv: Move) -> Self {
+        Self {
+            mv,
+            nags: Vec::new(),
+            comments_before: Vec::new(),
+            comments: Vec::new(),
+            variations: Vec::new(),
+        }
+    }
+}
+
+/// A game in [Portable Game Notation](https://www.chessprogramming.org/Portable_Game_Notation).
+///
+/// Writing a game with `Display` produces PGN export format: one tag pair per line, a blank line
+/// and movetext wrapped at 80 columns.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PgnGame {
+    /// Tag pairs in the order they were read or set.
+    pub tags: Vec<(String, String)>,
+    /// The position the movetext starts from, given by the `FEN` tag when present.
+    pub start: Position,
+    /// The main line.
+    pub moves: Vec<PgnMove>,
+    pub result: GameResult,
+}
+
+impl PgnGame {
+    /// Creates a game without moves, adding the `SetUp` and `FEN` tags unless `start` is the
+    /// standard starting position.
+    pub fn new(start: Position) -> Self {
+        let mut game = Self {
+            tags: Vec::new(),
+            start,
+            moves: Vec::new(),
+            result: GameResult::Unknown,
+        };
+        if game.start != Position::default() {
+            game.set_tag("SetUp", "1");
+            game.set_tag("FEN", &game.start.to_fen());
+        }
+
+        game
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Sets the value of a tag, replacing it in place or appending it if it is new.
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(tag, _)| tag == name) {
+            Some((_, old)) => *old = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    pub fn mainline(&self) -> impl Iterator<Item = Move> + '_ {
+        self.moves.iter().map(|node| node.mv)
+    }
+
+    /// Returns the start position followed by the position after each main line move.
+    pub fn positions(&self) -> Vec<Position> {
+        let mut position = self.start.clone();
+        let mut history = Vec::new();
+        let mut positions = vec![position.clone()];
+        for mv in self.mainline() {
+            position.make_move(mv, &mut history);
+            positions.push(position.clone());
+        }
+
+        positions
+    }
+}
+
+impl Default for PgnGame {
+    fn default() -> Self {
+        Self::new(Position::default())
+    }
+}
+
+impl Display for PgnGame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, value) in &self.tags {
+            let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+            writeln!(f, "[{name} \"{value}\"]")?;
+        }
+        if !self.tags.is_empty() {
+            writeln!(f)?;
+        }
+
+        let mut tokens = Vec::new();
+        push_line_tokens(&mut tokens, &self.start, &self.moves);
+        tokens.push(self.result.to_string());
+
+        let mut column = 0;
+        for token in tokens {
+            if column > 0 && column + 1 + token.len() > LINE_WIDTH {
+                writeln!(f)?;
+                column = 0;
+            } else if column > 0 {
+                write!(f, " ")?;
+                column += 1;
+            }
+
+            write!(f, "{token}")?;
+            column += token.len();
+        }
+
+        writeln!(f)
+    }
+}
+
+/// Appends the movetext tokens of a line of moves played from `start`, splitting comments into
+/// words so that they can be wrapped.
+fn push_line_tokens(tokens: &mut Vec<String>, start: &Position, moves: &[PgnMove]) {
+    let mut position = start.clone();
+    let mut history = Vec::new();
+    // Black moves only need a number at the start of a line or after an interruption
+    let mut interrupted = true;
+    for node in moves {
+        for comment in &node.comments_before {
+            push_comment_tokens(tokens, comment);
+            interrupted = true;
+        }
+
+        let number = position.full_move_number();
+        match position.side_to_move() {
+            Color::White => tokens.push(format!("{number}.")),
+            Color::Black if interrupted => tokens.push(format!("{number}...")),
+            Color::Black => {}
+        }
+        tokens.push(node.mv.to_san(&position));
+        tokens.extend(node.nags.iter().map(|nag| format!("${nag}")));
+        for comment in &node.comments {
+            push_comment_tokens(tokens, comment);
+        }
+
+        for variation in node.variations.iter().filter(|line| !line.is_empty()) {
+            let first = tokens.len();
+            push_line_tokens(tokens, &position, variation);
+            tokens[first].insert(0, '(');
+            tokens.last_mut().expect("variation has moves").push(')');
+        }
+
+        interrupted = !node.comments.is_empty() || !node.variations.is_empty();
+        position.make_move(node.mv, &mut history);
+    }
+}
+
+fn push_comment_tokens(tokens: &mut Vec<String>, comment: &str) {
+    let first = tokens.len();
+    tokens.extend(comment.split_whitespace().map(str::to_string));
+    if tokens.len() == first {
+        tokens.push("{}".to_string());
+    } else {
+        tokens[first].insert(0, '{');
+        tokens.last_mut().expect("comment has words").push('}');
+    }
+}
+
+/// Streams the games of a PGN file.
+///
+/// Moves are replayed as they are read, so every game yielded is legal. After a malformed game,
+/// the reader skips ahead to the next tag section, so one bad game does not end the stream.
+pub struct PgnReader<R> {
+    reader: R,
+    /// The current line, including its line break.
+    line: String,
+    /// Byte offset of the next character in `line`.
+    offset: usize,
+    line_number: usize,
+    done: bool,
+}
+
+impl<R: BufRead> PgnReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            line: String::new(),
+            offset: 0,
+            line_number: 0,
+            done: false,
+        }
+    }
+
+    fn read_game(&mut self) -> Result<Option<PgnGame>, Error> {
+        let mut tags = Vec::new();
+        loop {
+            self.skip_whitespace()?;
+            match self.peek()? {
+                Some('[') => tags.push(self.read_tag()?),
+                None if tags.is_empty() => return Ok(None),
+                _ => break,
+            }
+        }
+
+        let start = match tags.iter().find(|(name, _)| name == "FEN") {
+            Some((_, fen)) => Position::from_str(fen).map_err(|err| match err {
+                Error::InvalidFen { reason, .. } => self.error(PgnError::InvalidFen {
+                    fen: fen.clone(),
+                    reason,
+                }),
+                err => err,
+            })?,
+            None => Position::default(),
+        };
+
+        let (moves, result) = self.read_moves(&start, false)?;
+        let result = result
+            .or_else(|| {
+                tags.iter()
+                    .find(|(name, _)| name == "Result")
+                    .and_then(|(_, value)| value.parse().ok())
+            })
+            .unwrap_or_default();
+
+        Ok(Some(PgnGame {
+            tags,
+            start,
+            moves,
+            result,
+        }))
+    }
+
+    fn read_tag(&mut self) -> Result<(String, String), Error> {
+        self.bump();
+        self.skip_whitespace()?;
+        let name = self.read_symbol()?;
+        self.skip_whitespace()?;
+        if name.is_empty() || self.next_char()? != Some('"') {
+            return Err(self.error(PgnError::InvalidTag));
+        }
+
+        let mut value = String::new();
+        loop {
+            match self.next_char()? {
+                Some('"') => break,
+                Some('\\') => match self.next_char()? {
+                    Some(c) if c != '\n' => value.push(c),
+                    _ => return Err(self.error(PgnError::InvalidTag)),
+                },
+                Some(c) if c != '\n' => value.push(c),
+                _ => return Err(self.error(PgnError::InvalidTag)),
+            }
+        }
+
+        self.skip_whitespace()?;
+        if self.next_char()? != Some(']') {
+            return Err(self.error(PgnError::InvalidTag));
+        }
+
+        Ok((name, value))
+    }
+
+    /// Reads a line of moves played from `start` up to the result token, or up to the closing
+    /// parenthesis when the line is a variation.
+    ///
+    /// Comments of a line without moves are dropped.
+    fn read_moves(
+        &mut self,
+        start: &Position,
+        is_variation: bool,
+    ) -> Result<(Vec<PgnMove>, Option<GameResult>), Error> {
+        let mut position = start.clone();
+        let mut before_last = start.clone();
+        let mut history = Vec::new();
+        let mut moves: Vec<PgnMove> = Vec::new();
+        let mut pending_comments = Vec::new();
+        let result = loop {
+            self.skip_whitespace()?;
+            let Some(c) = self.peek()? else {
+                if is_variation {
+                    return Err(self.error(PgnError::UnclosedVariation));
+                }
+                break None;
+            };
+
+            match c {
+                '{' | ';' => {
+                    let comment = self.read_comment()?;
+                    match moves.last_mut() {
+                        Some(last) if pending_comments.is_empty() => last.comments.push(comment),
+                        _ => pending_comments.push(comment),
+                    }
+                }
+                '(' => {
+                    self.bump();
+                    if moves.is_empty() {
+                        return Err(self.error(PgnError::VariationWithoutMove));
+                    }
+                    let (variation, _) = self.read_moves(&before_last, true)?;
+                    if !variation.is_empty() {
+                        let last = moves.last_mut().expect("line has moves");
+                        last.variations.push(variation);
+                    }
+                }
+                ')' if is_variation => {
+                    self.bump();
+                    break None;
+                }
+                ')' => return Err(self.error(PgnError::UnmatchedParenthesis)),
+                '$' | '!' | '?' => {
+                    let nag = self.read_nag()?;
+                    match moves.last_mut() {
+                        Some(last) => last.nags.push(nag),
+                        None => return Err(self.error(PgnError::NagWithoutMove)),
+                    }
+                }
+                // The tag section of the next game, when this one has no result token
+                '[' if !is_variation => break None,
+                '.' => self.bump(),
+                '*' if is_variation => return Err(self.error(PgnError::UnclosedVariation)),
+                '*' => {
+                    self.bump();
+                    break Some(GameResult::Unknown);
+                }
+                c if is_symbol_char(c) => {
+                    let symbol = self.read_symbol()?;
+                    if let Ok(result) = symbol.parse::<GameResult>() {
+                        if is_variation {
+                            return Err(self.error(PgnError::UnclosedVariation));
+                        }
+                        break Some(result);
+                    }
+
+                    // Move numbers carry no information the replayed position lacks
+                    if symbol.bytes().all(|b| b.is_ascii_digit()) {
+                        continue;
+                    }
+
+                    let mv = Move::from_san(&symbol, &position).map_err(|err| match err {
+                        Error::InvalidSan { san, reason } => {
+                            self.error(PgnError::InvalidMove { san, reason })
+                        }
+                        err => err,
+                    })?;
+                    before_last = position.clone();
+                    position.make_move(mv, &mut history);
+
+                    let mut node = PgnMove::new(mv);
+                    node.comments_before = mem::take(&mut pending_comments);
+                    moves.push(node);
+                }
+                c => return Err(self.error(PgnError::UnexpectedChar(c))),
+            }
+        };
+
+        if let Some(last) = moves.last_mut() {
+            last.comments.append(&mut pending_comments);
+        }
+
+        Ok((moves, result))
+    }
+
+    /// Reads a `{...}` or `;` to end of line comment, collapsing whitespace so that line breaks
+    /// from wrapping do not survive a round trip.
+    fn read_comment(&mut self) -> Result<String, Error> {
+        let close = if self.next_char()? == Some('{') {
+            '}'
+        } else {
+            '\n'
+        };
+
+        let mut comment = String::new();
+        loop {
+            match self.next_char()? {
+                Some(c) if c == close => break,
+                Some(c) => comment.push(c),
+                None if close == '\n' => break,
+                None => return Err(self.error(PgnError::UnterminatedComment)),
+            }
+        }
+
+        Ok(comment.split_whitespace().collect::<Vec<_>>().join(" "))
+    }
+
+    /// Reads a `$n` annotation glyph or one of the `!`, `?`, `!!`, `??`, `!?` and `?!` suffixes
+    /// that stand for glyphs 1 to 6.
+    fn read_nag(&mut self) -> Result<u8, Error> {
+        let mut glyph = String::new();
+        if self.peek()? == Some('$') {
+            self.bump();
+            while let Some(c) = self.peek()?.filter(char::is_ascii_digit) {
+                glyph.push(c);
+                self.bump();
+            }
+
+            return glyph
+                .parse()
+                .map_err(|_| self.error(PgnError::InvalidNag(format!("${glyph}"))));
+        }
+
+        while let Some(c) = self.peek()?.filter(|&c| c == '!' || c == '?') {
+            glyph.push(c);
+            self.bump();
+        }
+
+        match glyph.as_str() {
+            "!" => Ok(1),
+            "?" => Ok(2),
+            "!!" => Ok(3),
+            "??" => Ok(4),
+            "!?" => Ok(5),
+            "?!" => Ok(6),
+            _ => Err(self.error(PgnError::InvalidNag(glyph))),
+        }
+    }
+
+    fn read_symbol(&mut self) -> Result<String, Error> {
+        let mut symbol = String::new();
+        while let Some(c) = self.peek()?.filter(|&c| is_symbol_char(c)) {
+            symbol.push(c);
+            self.bump();
+        }
+
+        Ok(symbol)
+    }
+
+    fn skip_whitespace(&mut self) -> Result<(), Error> {
+        while self.peek()?.is_some_and(char::is_whitespace) {
+            self.bump();
+        }
+
+        Ok(())
+    }
+
+    /// Returns the next character without consuming it, reading lines as needed.
+    fn peek(&mut self) -> Result<Option<char>, Error> {
+        loop {
+            if let Some(c) = self.line[self.offset..].chars().next() {
+                return Ok(Some(c));
+            }
+            if !self.read_line()? {
+                return Ok(None);
+            }
+        }
+    }
+
+    fn bump(&mut self) {
+        if let Some(c) = self.line[self.offset..].chars().next() {
+            self.offset += c.len_utf8();
+        }
+    }
+
+    fn next_char(&mut self) -> Result<Option<char>, Error> {
+        let c = self.peek()?;
+        self.bump();
+        Ok(c)
+    }
+
+    /// Replaces the current line with the next one, returning `false` at the end of input.
+    fn read_line(&mut self) -> Result<bool, Error> {
+        // Databases are not always UTF-8, and a stray Latin-1 name should not fail the game
+        let mut bytes = Vec::new();
+        if self.reader.read_until(b'\n', &mut bytes)? == 0 {
+            return Ok(false);
+        }
+
+        self.line = String::from_utf8_lossy(&bytes).into_owned();
+        self.line_number += 1;
+        // A '%' in the first column escapes the rest of the line
+        self.offset = if self.line.starts_with('%') {
+            self.line.len()
+        } else {
+            0
+        };
+
+        Ok(true)
+    }
+
+    /// Discards input up to the tag section of the next game.
+    fn skip_to_next_game(&mut self) {
+        let is_tag = |line: &str| line.trim_start().starts_with('[');
+        let mut seen_movetext = !is_tag(&self.line);
+        self.offset = self.line.len();
+        loop {
+            match self.read_line() {
+                Ok(true) if is_tag(&self.line) && seen_movetext => return,
+                Ok(true) => {
+                    seen_movetext |= !self.line.trim().is_empty() && !is_tag(&self.line);
+                    self.offset = self.line.len();
+                }
+                Ok(false) => return,
+                Err(_) => {
+                    self.done = true;
+                    return;
+                }
+            }
+        }
+    }
+
+    fn error(&self, reason: PgnError) -> Error {
+        Error::InvalidPgn {
+            line: self.line_number,
+            reason,
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for PgnReader<R> {
+    type Item = Result<PgnGame, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        match self.read_game() {
+            Ok(game) => game.map(Ok),
+            Err(err @ Error::Io(_)) => {
+                self.done = true;
+                Some(Err(err))
+            }
+            Err(err) => {
+                self.skip_to_next_game();
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+fn is_symbol_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '+' | '#' | '=' | ':' | '-' | '/')
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chess::{FenError, SanError, Square, movegen::MoveKind};
+
+    use super::*;
+
+    const GAME: &str = r#"[Event "Casual \"blitz\""]
+[Site "?"]
+[Date "2024.01.01"]
+[Round "-"]
+[White "A"]
+[Black "B"]
+[Result "1-0"]
+
+1. e4 e5 2. Nf3 {Developing} (2. f4 exf4 3. Nf3 $1) 2... Nc6 3. Bc4 Nd4?? 4. Nxe5
+Qg5 5. Nxf7 Qxg2 6. Rf1 Qxe4+ 7. Be2 Nf3# 1-0
+"#;
+
+    fn read_all(pgn: &str) -> Vec<Result<PgnGame, Error>> {
+        PgnReader::new(pgn.as_bytes()).collect()
+    }
+
+    fn pgn_error(pgn: &str) -> PgnError {
+        match read_all(pgn).remove(0) {
+            Err(Error::InvalidPgn { reason, .. }) => reason,
+            result => panic!("expected PGN error, got {result:?}"),
+        }
+    }
+
+    #[test]
+    fn test_read_game() {
+        let game = read_all(GAME).remove(0).unwrap();
+
+        assert_eq!(Some("Casual \"blitz\""), game.tag("Event"));
+        assert_eq!(7, game.tags.len());
+        assert_eq!(GameResult::WhiteWins, game.result);
+        assert_eq!(14, game.moves.len());
+        assert_eq!(
+            Move::new(Square::E2, Square::E4, MoveKind::Quiet),
+            game.moves[0].mv
+        );
+        assert_eq!(vec!["Developing"], game.moves[2].comments);
+        assert_eq!(vec![4], game.moves[5].nags);
+
+        let variation = &game.moves[2].variations[0];
+        assert_eq!(3, variation.len());
+        assert_eq!(
+            Move::new(Square::F2, Square::F4, MoveKind::Quiet),
+            variation[0].mv
+        );
+        assert_eq!(vec![1], variation[2].nags);
+
+        let end = game.positions().pop().unwrap();
+        assert_eq!(
+            "r1b1kbnr/pppp1Npp/8/8/4q3/5n2/PPPPBP1P/RNBQKR2 w Qkq - 2 8",
+            end.to_fen()
+        );
+    }
+
+    #[test]
+    fn test_read_multiple_games() {
+        let pgn = format!(
+            "{GAME}\n[Event \"Second\"]\n[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/4P3/4K3 b - - 0 1\"]\n\n\
+             1... Kd7 {{last}} ; rest of line\n2. e4 *\n\n1. d4 (1. c4 (1. Nf3) {{English}}) d5"
+        );
+        let games: Vec<PgnGame> = read_all(&pgn).into_iter().map(Result::unwrap).collect();
+
+        assert_eq!(3, games.len());
+        assert_eq!(Some("Second"), games[1].tag("Event"));
+        assert_eq!(2, games[1].moves.len());
+        assert_eq!(vec!["last", "rest of line"], games[1].moves[0].comments);
+        assert_eq!(GameResult::Unknown, games[1].result);
+        assert_eq!(0, games[2].tags.len());
+        assert_eq!(2, games[2].moves.len());
+        assert_eq!(1, games[2].moves[0].variations[0][0].variations.len());
+        assert_eq!(vec!["English"], games[2].moves[0].variations[0][0].comments);
+    }
+
+    #[test]
+    fn test_read_errors() {
+        assert_eq!(PgnError::InvalidTag, pgn_error("[Event \"open\n\n1. e4 *"));
+        assert_eq!(PgnError::UnterminatedComment, pgn_error("1. e4 {open"));
+        assert_eq!(PgnError::UnmatchedParenthesis, pgn_error("1. e4 ) *"));
+        assert_eq!(PgnError::UnclosedVariation, pgn_error("1. e4 (1. d4 *"));
+        assert_eq!(PgnError::VariationWithoutMove, pgn_error("(1. e4) *"));
+        assert_eq!(PgnError::NagWithoutMove, pgn_error("$1 1. e4 *"));
+        assert_eq!(
+            PgnError::InvalidNag("!!!".to_string()),
+            pgn_error("1. e4!!! *")
+        );
+        assert_eq!(PgnError::UnexpectedChar('&'), pgn_error("1. e4 & *"));
+        assert_eq!(
+            PgnError::InvalidMove {
+                san: "e5".to_string(),
+                reason: SanError::Illegal
+            },
+            pgn_error("1. e4 e5 2. e5 *")
+        );
+        assert_eq!(
+            PgnError::InvalidFen {
+                fen: "8/8/8/8/8/8/8/8 w - - 0 1".to_string(),
+                reason: FenError::KingCount {
+                    color: Color::White,
+                    count: 0
+                }
+            },
+            pgn_error("[FEN \"8/8/8/8/8/8/8/8 w - - 0 1\"]\n\n*")
+        );
+    }
+
+    #[test]
+    fn test_recover_after_error() {
+        let pgn = format!("[Event \"Bad\"]\n\n1. e4 e4 2. d4 *\n\n{GAME}");
+        let games = read_all(&pgn);
+
+        assert_eq!(2, games.len());
+        assert!(matches!(games[0], Err(Error::InvalidPgn { line: 3, .. })));
+        assert_eq!(Some("A"), games[1].as_ref().unwrap().tag("White"));
+    }
+
+    #[test]
+    fn test_write_round_trip() {
+        let game = read_all(GAME).remove(0).unwrap();
+        let written = game.to_string();
+
+        assert!(written.starts_with("[Event \"Casual \\\"blitz\\\"\"]\n[Site \"?\"]\n"));
+        assert!(
+            written.contains("\n\n1. e4 e5 2. Nf3 {Developing} (2. f4 exf4 3. Nf3 $1) 2... Nc6")
+        );
+        assert!(written.contains("Nd4 $4"));
+        assert!(written.ends_with("Nf3# 1-0\n"));
+        assert!(written.lines().all(|line| line.len() <= LINE_WIDTH));
+        assert_eq!(game, read_all(&written).remove(0).unwrap());
+    }
+
+    #[test]
+    fn test_write_wraps_long_movetext() {
+        let mut game = PgnGame::new(Position::default());
+        let mut position = game.start.clone();
+        for san in ["Nf3", "Nf6", "Ng1", "Ng8"].iter().cycle().take(60) {
+            let mv = Move::from_san(san, &position).unwrap();
+            position.make_move(mv, &mut Vec::new());
+            let mut node = PgnMove::new(mv);
+            node.comments
+                .push("a comment long enough to need wrapping".to_string());
+            game.moves.push(node);
+        }
+        let written = game.to_string();
+
+        assert!(written.lines().count() > 10);
+        assert!(written.lines().all(|line| line.len() <= LINE_WIDTH));
+        assert!(written.contains("1... Nf6"));
+        assert_eq!(game, read_all(&written).remove(0).unwrap());
+    }
+
+    #[test]
+    fn test_new_game_from_setup_position() {
+        let start = Position::from_str("4k3/8/8/8/8/8/4P3/4K3 b - - 0 1").unwrap();
+        let game = PgnGame::new(start);
+
+        assert_eq!(Some("1"), game.tag("SetUp"));
+        assert_eq!(Some("4k3/8/8/8/8/8/4P3/4K3 b - - 0 1"), game.tag("FEN"));
+        assert_eq!(game, read_all(&game.to_string()).remove(0).unwrap());
+    }
+}
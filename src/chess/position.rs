@@ -1,10 +1,13 @@
 use std::{fmt::Display, str::FromStr};
 
 use crate::chess::{
-    Bitboard, CastlingRights, Color, File, NUM_COLORS, NUM_FILES, NUM_PIECES, NUM_RANKS, Piece,
-    PieceType, Rank, Square,
-    error::Error,
-    movegen::{Move, MoveKind, attack_mask, bishop_attacks, pawn_attack_mask, rook_attacks},
+    Bitboard, CastlingRights, Color, File, NUM_COLORS, NUM_FILES, NUM_PIECES, NUM_RANKS,
+    NUM_SQUARES, Piece, PieceType, Rank, Square,
+    error::{Error, FenError, FenField},
+    movegen::{
+        Move, MoveKind, attack_mask, between, bishop_attacks, pawn_attack_mask, rook_attacks,
+    },
+    zobrist,
 };
 
 pub const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
@@ -14,10 +17,13 @@ pub(crate) const NUM_BITBOARDS: usize = NUM_COLORS * NUM_PIECES;
 /// State that cannot be recovered by the inverse of a move alone.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct State {
+    hash: u64,
     castling_rights: [CastlingRights; NUM_COLORS],
     en_passant_square: Option<Square>,
     half_move_clock: u8,
     captured_piece: Option<Piece>,
+    /// Whether the state was saved by a null move rather than a move on the board.
+    null_move: bool,
 }
 
 /// Representation of a chess position.
@@ -26,16 +32,29 @@ pub struct State {
 /// state of an entire chess board during a particular point in the game. One can think of it as
 /// a snapshot or picture of the board at a given moment. This includes piece placement, side to
 /// move, castling rights, an optional en passant square, and the half-move clock. Move history
-/// is not a part of the position itself and is tracked as part of an entire game. Practically
-/// all rules can be applied based on the position alone. The only rule that cannot be applied
-/// from a position is the determination of three fold repititions.
+/// is not a part of the position itself and is tracked as part of an entire game, see
+/// [`Game`](crate::chess::Game). Practically all rules can be applied based on the position
+/// alone. The only rule that cannot be applied from a position is the determination of
+/// repetitions.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Position {
     bitboards: [Bitboard; NUM_BITBOARDS],
+    /// The piece on each square, kept in step with `bitboards` for constant time lookups.
+    mailbox: [Option<Piece>; NUM_SQUARES],
+    /// All pieces of each color, kept in step with `bitboards`.
+    occupancy: [Bitboard; NUM_COLORS],
     side_to_move: Color,
     castling_rights: [CastlingRights; NUM_COLORS],
+    /// Files of the rooks each color castles with, king side first. They only differ from the
+    /// h and a files in Chess960.
+    castling_rook_files: [[File; 2]; NUM_COLORS],
+    /// Whether castling is written in Chess960 style, as X-FEN castling fields and UCI moves of
+    /// the king onto its rook.
+    chess960: bool,
     en_passant_square: Option<Square>,
     half_move_clock: u8,
+    full_move_number: u16,
+    hash: u64,
 }
 
 const RANK_DIVIDER: &str = "+---+---+---+---+---+---+---+---+";
@@ -85,10 +104,13 @@ impl Position {
         self.side_to_move
     }
 
+    /// Returns the Zobrist hash of the position.
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
     pub fn occupied(&self) -> Bitboard {
-        self.bitboards
-            .iter()
-            .fold(Bitboard::EMPTY, |acc, bb| acc | *bb)
+        self.occupancy[Color::White as usize] | self.occupancy[Color::Black as usize]
     }
 
     pub fn empty_squares(&self) -> Bitboard {
@@ -108,41 +130,121 @@ impl Position {
         self.en_passant_square
     }
 
+    pub fn half_move_clock(&self) -> u8 {
+        self.half_move_clock
+    }
+
+    /// Returns the number of the current full move, which starts at 1 and increments after
+    /// every black move.
+    pub fn full_move_number(&self) -> u16 {
+        self.full_move_number
+    }
+
     pub fn castling_rights(&self, color: Color) -> CastlingRights {
         self.castling_rights[color]
     }
 
-    pub fn color_pieces(&self, color: Color) -> Bitboard {
-        let range = if color.is_white() {
-            0..NUM_PIECES
+    /// Returns the square of the rook `color` may still castle with on `side`, which is either
+    /// [`CastlingRights::KingSide`] or [`CastlingRights::QueenSide`]. Any other `side` names no
+    /// single rook and returns `None`.
+    pub fn castling_rook(&self, color: Color, side: CastlingRights) -> Option<Square> {
+        let index = match side {
+            CastlingRights::KingSide => 0,
+            CastlingRights::QueenSide => 1,
+            CastlingRights::All | CastlingRights::None => return None,
+        };
+        let home_rank = if color.is_white() {
+            Rank::One
         } else {
-            NUM_PIECES..NUM_BITBOARDS
+            Rank::Eight
         };
-        let mut combined_pieces = Bitboard::EMPTY;
-        for i in range {
-            combined_pieces |= self.bitboards[i];
-        }
 
-        combined_pieces
+        self.castling_rights[color]
+            .contains(side)
+            .then(|| Square::new(self.castling_rook_files[color as usize][index], home_rank))
     }
 
-    pub fn get_piece_at(&self, square: &Square) -> Option<Piece> {
-        let mut piece_opt = None;
-        let square_bb = Bitboard::from(*square);
-        for (i, bb) in self.bitboards.iter().enumerate() {
-            if *bb & square_bb != Bitboard::EMPTY {
-                let color = if i < NUM_PIECES {
-                    Color::White
-                } else {
-                    Color::Black
-                };
+    pub fn is_chess960(&self) -> bool {
+        self.chess960
+    }
 
-                piece_opt = Some(Piece::new(color, PieceType::try_from(i % NUM_PIECES).ok()?));
-                break;
-            }
+    /// Sets whether castling is written in Chess960 style. Positions read from a FEN that can
+    /// only describe Chess960 castling are always Chess960.
+    pub fn set_chess960(&mut self, chess960: bool) {
+        self.chess960 = chess960;
+    }
+
+    /// Creates the Chess960 starting position with the given
+    /// [Scharnagl number](https://www.chessprogramming.org/Reinhard_Scharnagl#Chess960_Numbering_Scheme)
+    /// from 0 to 959, where 518 is the standard starting position.
+    pub fn from_chess960_index(index: u16) -> Result<Self, Error> {
+        // The two knights' places among the five squares left after the bishops and queen
+        const KNIGHT_PLACEMENTS: [(usize, usize); 10] = [
+            (0, 1),
+            (0, 2),
+            (0, 3),
+            (0, 4),
+            (1, 2),
+            (1, 3),
+            (1, 4),
+            (2, 3),
+            (2, 4),
+            (3, 4),
+        ];
+
+        if index >= 960 {
+            return Err(Error::InvalidIndex);
+        }
+
+        let mut back_rank = [None; NUM_FILES];
+        let mut n = index as usize;
+        back_rank[n % 4 * 2 + 1] = Some(PieceType::Bishop);
+        n /= 4;
+        back_rank[n % 4 * 2] = Some(PieceType::Bishop);
+        n /= 4;
+
+        let empty_files = |back_rank: &[Option<PieceType>; NUM_FILES]| -> Vec<usize> {
+            (0..NUM_FILES)
+                .filter(|&file| back_rank[file].is_none())
+                .collect()
+        };
+        back_rank[empty_files(&back_rank)[n % 6]] = Some(PieceType::Queen);
+        n /= 6;
+
+        let empty = empty_files(&back_rank);
+        let (first, second) = KNIGHT_PLACEMENTS[n];
+        back_rank[empty[first]] = Some(PieceType::Knight);
+        back_rank[empty[second]] = Some(PieceType::Knight);
+        for (file, piece_type) in empty_files(&back_rank).into_iter().zip([
+            PieceType::Rook,
+            PieceType::King,
+            PieceType::Rook,
+        ]) {
+            back_rank[file] = Some(piece_type);
         }
 
-        piece_opt
+        let white: String = back_rank
+            .iter()
+            .map(|piece_type| {
+                Piece::new(Color::White, piece_type.expect("back rank is full")).to_string()
+            })
+            .collect();
+        let fen = format!(
+            "{}/pppppppp/8/8/8/8/PPPPPPPP/{white} w KQkq - 0 1",
+            white.to_lowercase()
+        );
+
+        let mut position = Self::from_str(&fen)?;
+        position.chess960 = true;
+        Ok(position)
+    }
+
+    pub fn color_pieces(&self, color: Color) -> Bitboard {
+        self.occupancy[color as usize]
+    }
+
+    pub fn get_piece_at(&self, square: &Square) -> Option<Piece> {
+        self.mailbox[*square as usize]
     }
 
     /// Attempts to make a move.
@@ -159,7 +261,12 @@ impl Position {
         let to = mv.to_sq();
         let kind = mv.kind();
         let moved_piece = self.get_piece_at(&from).expect("no piece at from square");
-        let captured_piece = self.get_piece_at(&to);
+        // Castling moves the king onto its own rook, which is not a capture
+        let captured_piece = if kind == MoveKind::Castle {
+            None
+        } else {
+            self.get_piece_at(&to)
+        };
 
         debug_assert_eq!(
             moved_piece.color(),
@@ -167,71 +274,61 @@ impl Position {
             "moved piece is not of the expected color"
         );
 
-        // Confirm we aren't doing a castle in check to avoid making updates
-        if mv.kind() == MoveKind::Castle && self.is_checked(us) {
+        // Confirm we aren't castling out of or through check to avoid making updates
+        if kind == MoveKind::Castle && !self.is_castling_path_safe(mv) {
             return false;
         }
 
-        // Update the moved piece bitboard
-        let moved_piece_bb = self.piece_mut(moved_piece);
-        *moved_piece_bb ^= from.into();
-        *moved_piece_bb ^= to.into();
+        // Remove the keys of state that may change, they are added back once it is updated
+        self.hash ^= self.state_key();
 
-        // Handle special move cases
+        // Update the board, taking captured pieces off before anything lands on their square
         match kind {
-            MoveKind::Capture => {
-                let captured_piece = captured_piece
-                    .expect("captured piece does not exist on internal board representation");
-                debug_assert_eq!(
-                    captured_piece.color(),
-                    them,
-                    "Captured piece is not of the opposite color"
-                );
-
-                let captured_piece_bb = self.piece_mut(captured_piece);
-                *captured_piece_bb ^= to.into();
-                saved_state.captured_piece = Some(captured_piece);
-            }
             MoveKind::EnPassant => {
                 debug_assert_ne!(self.en_passant_square(), None);
                 let ep_target = self
                     .en_passant_square()
                     .expect("no En Passant target for pseudo-legal move to be possible");
                 let capture_square = Square::new(ep_target.file(), from.rank());
-                *self.piece_mut(Piece::new(them, PieceType::Pawn)) ^= capture_square.into();
+                self.remove_piece(Piece::new(them, PieceType::Pawn), capture_square);
+                self.remove_piece(moved_piece, from);
+                self.put_piece(moved_piece, to);
                 saved_state.captured_piece = Some(Piece::new(them, PieceType::Pawn));
             }
             MoveKind::Castle => {
-                let king_side = to.file() == File::G;
-                let rook_destination =
-                    Square::new(if king_side { File::F } else { File::D }, to.rank());
-                let rooks_bb = self.piece_mut(Piece::new(us, PieceType::Rook));
-                let mut rook_mask = (Bitboard(1) << (if king_side { 7 } else { 0 }))
-                    << if us.is_white() { 0 } else { 56 };
-                rook_mask |= rook_destination.into();
-                *rooks_bb ^= rook_mask;
+                // Lift both pieces before placing them, as in Chess960 either may land on the
+                // square the other left
+                let (king_destination, rook_destination) = mv.castling_squares();
+                let rook = Piece::new(us, PieceType::Rook);
+                self.remove_piece(moved_piece, from);
+                self.remove_piece(rook, to);
+                self.put_piece(moved_piece, king_destination);
+                self.put_piece(rook, rook_destination);
                 self.castling_rights[us] = CastlingRights::None;
-                if self.is_attacked(rook_destination, them) {
-                    is_legal = false;
-                }
             }
-            MoveKind::Promotion(piece_type) => {
-                *self.piece_mut(moved_piece) ^= to.into();
-                *self.piece_mut(Piece::new(us, piece_type)) ^= to.into();
-
-                // We moved the pawn which may be causing issues
-                if from.file() != to.file() {
-                    let captured_piece = captured_piece
-                        .expect("captured EP/Promo piece not in board representation");
-
-                    *self.piece_mut(captured_piece) ^= to.into();
+            _ => {
+                debug_assert!(
+                    kind != MoveKind::Capture || captured_piece.is_some(),
+                    "captured piece does not exist on internal board representation"
+                );
+                if let Some(captured_piece) = captured_piece {
+                    debug_assert_eq!(
+                        captured_piece.color(),
+                        them,
+                        "Captured piece is not of the opposite color"
+                    );
+
+                    self.remove_piece(captured_piece, to);
+                    saved_state.captured_piece = Some(captured_piece);
                 }
 
-                if captured_piece.is_some() {
-                    saved_state.captured_piece = captured_piece;
-                }
+                let placed_piece = match kind {
+                    MoveKind::Promotion(piece_type) => Piece::new(us, piece_type),
+                    _ => moved_piece,
+                };
+                self.remove_piece(moved_piece, from);
+                self.put_piece(placed_piece, to);
             }
-            _ => (),
         }
 
         // Update en-passant square
@@ -278,10 +375,20 @@ impl Position {
             if mv.kind() == MoveKind::Capture || moved_piece.piece_type() == PieceType::Pawn {
                 0
             } else {
-                self.half_move_clock + 1
+                self.half_move_clock.saturating_add(1)
             };
 
+        if !us.is_white() {
+            self.full_move_number += 1;
+        }
+
         self.side_to_move = !self.side_to_move;
+        self.hash ^= zobrist::side_to_move_key() ^ self.state_key();
+        debug_assert_eq!(self.hash, self.compute_hash(), "incremental hash diverged");
+        debug_assert!(
+            self.is_board_consistent(),
+            "mailbox diverged from bitboards"
+        );
 
         history.push(saved_state);
         if is_legal && self.is_checked(us) {
@@ -297,11 +404,260 @@ impl Position {
 
     /// Returns whether a specified side is in check
     pub fn is_checked(&self, side: Color) -> bool {
-        let king_square = self
-            .piece(Piece::new(side, PieceType::King))
-            .pop_lsb()
-            .expect("no king on board");
-        self.is_attacked(king_square, !side)
+        self.is_attacked(self.king_square(side), !side)
+    }
+
+    /// Returns the square of a specified side's king
+    pub fn king_square(&self, side: Color) -> Square {
+        self.piece(Piece::new(side, PieceType::King))
+            .lsb()
+            .expect("no king on board")
+    }
+
+    /// Returns the enemy pieces giving check to the side to move
+    pub fn checkers(&self) -> Bitboard {
+        let us = self.side_to_move();
+        self.attackers_to(self.king_square(us), self.occupied()) & self.color_pieces(!us)
+    }
+
+    /// Returns whether a pseudo-legal move of the side to move would check the enemy king, either
+    /// directly or by uncovering an attack from one of its sliders.
+    pub fn gives_check(&self, mv: Move) -> bool {
+        let us = self.side_to_move();
+        let king = self.king_square(!us);
+        let from = mv.from_sq();
+        let to = mv.to_sq();
+
+        // Board after the move, and the square and type of the piece that may check directly
+        let mut occupied = self.occupied() ^ Bitboard::from(from);
+        let mut moved = Bitboard::from(from);
+        let (checker, checker_type) = match mv.kind() {
+            MoveKind::Castle => {
+                let (king_destination, rook_destination) = mv.castling_squares();
+                occupied ^= Bitboard::from(to);
+                occupied |= Bitboard::from(king_destination) | rook_destination.into();
+                moved |= to.into();
+                (rook_destination, PieceType::Rook)
+            }
+            MoveKind::EnPassant => {
+                occupied ^= Square::new(to.file(), from.rank()).into();
+                occupied |= to.into();
+                (to, PieceType::Pawn)
+            }
+            MoveKind::Promotion(piece_type) => {
+                occupied |= to.into();
+                (to, piece_type)
+            }
+            _ => {
+                occupied |= to.into();
+                let piece = self.get_piece_at(&from).expect("no piece at from square");
+                (to, piece.piece_type())
+            }
+        };
+
+        let direct = match checker_type {
+            PieceType::Pawn => pawn_attack_mask(us, checker),
+            PieceType::Bishop => bishop_attacks(checker, occupied),
+            PieceType::Rook => rook_attacks(checker, occupied),
+            PieceType::Queen => bishop_attacks(checker, occupied) | rook_attacks(checker, occupied),
+            piece_type => attack_mask(piece_type, checker),
+        };
+        if direct & king.into() != Bitboard::EMPTY {
+            return true;
+        }
+
+        let queens = self.piece(Piece::new(us, PieceType::Queen));
+        let diagonal = (self.piece(Piece::new(us, PieceType::Bishop)) | queens) & !moved;
+        let orthogonal = (self.piece(Piece::new(us, PieceType::Rook)) | queens) & !moved;
+        (bishop_attacks(king, occupied) & diagonal) | (rook_attacks(king, occupied) & orthogonal)
+            != Bitboard::EMPTY
+    }
+
+    /// Returns the material the side to move gains by a move once every capture on its
+    /// destination has been resolved, with each side recapturing with its least valuable piece
+    /// and free to stop whenever continuing would lose material.
+    ///
+    /// Sliders lined up behind the capturing pieces join in as the pieces in front of them are
+    /// traded off. Pins are ignored. Castling and quiet moves onto safe squares are worth zero.
+    pub fn see(&self, mv: Move) -> i32 {
+        if mv.kind() == MoveKind::Castle {
+            return 0;
+        }
+
+        let to = mv.to_sq();
+        let (captured, mut attacker) = self.see_capture(mv);
+        let mut occupied = self.see_occupancy(mv);
+        let mut side = self.side_to_move();
+
+        // Each entry is the balance for the side making that capture, should the exchange stop
+        // there. Every capture removes a piece, so there can be no more entries than pieces.
+        let mut gain = [0; 32];
+        gain[0] = captured;
+        let mut depth = 0;
+        loop {
+            // The gain if the opponent takes the piece just placed on the square
+            depth += 1;
+            gain[depth] = attacker.value() - gain[depth - 1];
+
+            side = !side;
+            let attackers = self.attackers_to(to, occupied) & occupied;
+            let Some((square, piece_type)) = self.least_valuable_attacker(attackers, side) else {
+                break;
+            };
+
+            // A king may not capture onto a defended square
+            if piece_type == PieceType::King
+                && attackers & self.color_pieces(!side) != Bitboard::EMPTY
+            {
+                break;
+            }
+
+            occupied ^= square.into();
+            attacker = piece_type;
+        }
+
+        // The last entry assumes a capture no one can make, so it is discarded
+        while depth > 1 {
+            depth -= 1;
+            gain[depth - 1] = -(-gain[depth - 1]).max(gain[depth]);
+        }
+
+        gain[0]
+    }
+
+    /// Returns whether the [static exchange evaluation](Position::see) of a move is at least
+    /// `threshold`, stopping as soon as the outcome is certain.
+    pub fn see_ge(&self, mv: Move, threshold: i32) -> bool {
+        if mv.kind() == MoveKind::Castle {
+            return 0 >= threshold;
+        }
+
+        let to = mv.to_sq();
+        let (captured, attacker) = self.see_capture(mv);
+
+        // Even keeping the capture uncontested falls short
+        let mut swap = captured - threshold;
+        if swap < 0 {
+            return false;
+        }
+
+        // Even losing the capturing piece for nothing clears the threshold
+        swap = attacker.value() - swap;
+        if swap <= 0 {
+            return true;
+        }
+
+        let mut occupied = self.see_occupancy(mv);
+        let mut side = self.side_to_move();
+        let mut result = true;
+        loop {
+            side = !side;
+            let attackers = self.attackers_to(to, occupied) & occupied;
+            let Some((square, piece_type)) = self.least_valuable_attacker(attackers, side) else {
+                break;
+            };
+
+            // A king may not capture onto a defended square
+            if piece_type == PieceType::King {
+                return if attackers & self.color_pieces(!side) != Bitboard::EMPTY {
+                    result
+                } else {
+                    !result
+                };
+            }
+
+            // `swap` is what the side to capture stands to lose if the piece it captures with is
+            // taken in turn, once it is negative that side cannot come out ahead
+            result = !result;
+            swap = piece_type.value() - swap;
+            if swap < result as i32 {
+                break;
+            }
+
+            occupied ^= square.into();
+        }
+
+        result
+    }
+
+    /// Returns the material a move wins outright and the type of the piece it leaves on its
+    /// destination.
+    fn see_capture(&self, mv: Move) -> (i32, PieceType) {
+        let moved = self
+            .get_piece_at(&mv.from_sq())
+            .expect("no piece at from square")
+            .piece_type();
+        match mv.kind() {
+            MoveKind::EnPassant => (PieceType::Pawn.value(), moved),
+            MoveKind::Promotion(piece_type) => {
+                let captured = self
+                    .get_piece_at(&mv.to_sq())
+                    .map_or(0, |piece| piece.piece_type().value());
+                (
+                    captured + piece_type.value() - PieceType::Pawn.value(),
+                    piece_type,
+                )
+            }
+            _ => {
+                let captured = self
+                    .get_piece_at(&mv.to_sq())
+                    .map_or(0, |piece| piece.piece_type().value());
+                (captured, moved)
+            }
+        }
+    }
+
+    /// Returns the occupancy after a move, before any recapture.
+    fn see_occupancy(&self, mv: Move) -> Bitboard {
+        let from = mv.from_sq();
+        let to = mv.to_sq();
+        let mut occupied = (self.occupied() ^ Bitboard::from(from)) | to.into();
+        if mv.kind() == MoveKind::EnPassant {
+            occupied ^= Square::new(to.file(), from.rank()).into();
+        }
+
+        occupied
+    }
+
+    /// Returns the square and type of the least valuable of `side`'s pieces among `attackers`.
+    fn least_valuable_attacker(
+        &self,
+        attackers: Bitboard,
+        side: Color,
+    ) -> Option<(Square, PieceType)> {
+        [
+            PieceType::Pawn,
+            PieceType::Knight,
+            PieceType::Bishop,
+            PieceType::Rook,
+            PieceType::Queen,
+            PieceType::King,
+        ]
+        .into_iter()
+        .find_map(|piece_type| {
+            let square = (attackers & self.piece(Piece::new(side, piece_type))).lsb()?;
+            Some((square, piece_type))
+        })
+    }
+
+    /// Returns whether neither side can checkmate with any sequence of legal moves: a bare king
+    /// against at most one minor piece, or only bishops that all stand on squares of one color.
+    pub fn has_insufficient_material(&self) -> bool {
+        const DARK_SQUARES: Bitboard = Bitboard(0xAA55_AA55_AA55_AA55);
+
+        let heavy_pieces = self.piece_type(PieceType::Pawn)
+            | self.piece_type(PieceType::Rook)
+            | self.piece_type(PieceType::Queen);
+        if heavy_pieces != Bitboard::EMPTY {
+            return false;
+        }
+
+        let knights = self.piece_type(PieceType::Knight);
+        let bishops = self.piece_type(PieceType::Bishop);
+        (knights | bishops).count() <= 1
+            || (knights == Bitboard::EMPTY
+                && ((bishops & DARK_SQUARES) == Bitboard::EMPTY
+                    || (bishops & !DARK_SQUARES) == Bitboard::EMPTY))
     }
 
     /// Attempts to unmake a move
@@ -318,199 +674,453 @@ impl Position {
         let us = !them;
         let from = mv.to_sq();
         let to = mv.from_sq();
+
+        // The board updates below toggle the hash, which is then overwritten by the saved one
+        if mv.kind() == MoveKind::Castle {
+            let (king_destination, rook_destination) = mv.castling_squares();
+            let king = Piece::new(us, PieceType::King);
+            let rook = Piece::new(us, PieceType::Rook);
+            self.remove_piece(king, king_destination);
+            self.remove_piece(rook, rook_destination);
+            self.put_piece(king, to);
+            self.put_piece(rook, from);
+            self.restore_state(state, us);
+            return;
+        }
+
         let moved_piece = self
             .get_piece_at(&from)
             .expect("no piece at moved location");
 
-        // Move piece back to original square
-        let moved_piece_bb = self.piece_mut(moved_piece);
-        *moved_piece_bb ^= from.into();
-        *moved_piece_bb ^= to.into();
-
-        // Handle special case moves
-        match mv.kind() {
-            MoveKind::EnPassant => {
-                debug_assert!(state.en_passant_square.is_some());
-                debug_assert_eq!(state.en_passant_square.unwrap(), from);
-                debug_assert!(state.captured_piece.is_some());
-                debug_assert_eq!(
-                    state.captured_piece.unwrap(),
-                    Piece::new(them, PieceType::Pawn)
-                );
+        // Move piece back to original square, demoting it if it promoted
+        let original_piece = match mv.kind() {
+            MoveKind::Promotion(_) => Piece::new(us, PieceType::Pawn),
+            _ => moved_piece,
+        };
+        self.remove_piece(moved_piece, from);
+        self.put_piece(original_piece, to);
 
-                let ep_rank = if us.is_white() {
-                    Rank::Five
-                } else {
-                    Rank::Four
-                };
+        // Place captured piece back on square
+        if mv.kind() == MoveKind::EnPassant {
+            debug_assert!(state.en_passant_square.is_some());
+            debug_assert_eq!(state.en_passant_square.unwrap(), from);
+            debug_assert!(state.captured_piece.is_some());
+            debug_assert_eq!(
+                state.captured_piece.unwrap(),
+                Piece::new(them, PieceType::Pawn)
+            );
+
+            let ep_rank = if us.is_white() {
+                Rank::Five
+            } else {
+                Rank::Four
+            };
 
-                let captured_piece = state
-                    .captured_piece
-                    .expect("no taken pawn stored for En Passant");
-                let en_passant_square = Square::new(from.file(), ep_rank);
-                *self.piece_mut(captured_piece) ^= en_passant_square.into();
-            }
-            MoveKind::Castle => {
-                // Just need to restore rook back to home file
-                let king_file = from.file();
-                let king_rank = from.rank();
-                debug_assert!(
-                    (king_rank == Rank::One && us.is_white())
-                        || (king_rank == Rank::Eight && !us.is_white())
-                );
-                let (rook_home_file, rook_castle_file) = if king_file == File::C {
-                    (File::A, File::D)
-                } else {
-                    (File::H, File::F)
-                };
+            let captured_piece = state
+                .captured_piece
+                .expect("no taken pawn stored for En Passant");
+            let en_passant_square = Square::new(from.file(), ep_rank);
+            self.put_piece(captured_piece, en_passant_square);
+        } else if let Some(piece) = state.captured_piece {
+            self.put_piece(piece, from);
+        }
 
-                let rook_castle_sq = Square::new(rook_castle_file, king_rank);
-                let rook_home_sq = Square::new(rook_home_file, king_rank);
-                let rooks_bb = self.piece_mut(Piece::new(us, PieceType::Rook));
-                *rooks_bb ^= rook_castle_sq.into();
-                *rooks_bb ^= rook_home_sq.into();
-            }
-            MoveKind::Promotion(_) => {
-                // Swap the piece type for the piece previously moved to to
-                *moved_piece_bb ^= to.into();
+        self.restore_state(state, us);
+    }
 
-                // Change moved piece over to pawn
-                let pawn = Piece::new(us, PieceType::Pawn);
-                *self.piece_mut(pawn) ^= to.into();
-            }
-            _ => (),
+    /// Passes the turn to the opponent without moving a piece, as null move pruning does.
+    ///
+    /// The side to move must not be in check, or the opponent could capture the king.
+    pub fn make_null_move(&mut self, history: &mut Vec<State>) {
+        debug_assert!(
+            !self.is_checked(self.side_to_move()),
+            "null move made in check"
+        );
+        history.push(State {
+            null_move: true,
+            ..self.board_state()
+        });
+
+        self.hash ^= self.state_key();
+        self.en_passant_square = None;
+        self.half_move_clock = self.half_move_clock.saturating_add(1);
+        if !self.side_to_move.is_white() {
+            self.full_move_number += 1;
         }
 
-        // Place captured piece back on square
-        if let Some(piece) = state.captured_piece
-            && mv.kind() != MoveKind::EnPassant
-        {
-            *self.piece_mut(piece) ^= from.into();
-        }
+        self.side_to_move = !self.side_to_move;
+        self.hash ^= zobrist::side_to_move_key() ^ self.state_key();
+        debug_assert_eq!(self.hash, self.compute_hash(), "incremental hash diverged");
+    }
+
+    /// Takes back a move made by [`Position::make_null_move`].
+    pub fn unmake_null_move(&mut self, history: &mut Vec<State>) {
+        let state = history.pop().expect("history length of 0");
+        self.restore_state(state, !self.side_to_move);
+    }
 
-        // Restore remaining board state
+    /// Returns whether `side` has any pieces besides pawns and its king.
+    pub fn has_non_pawn_material(&self, side: Color) -> bool {
+        let pawns_and_king = self.piece(Piece::new(side, PieceType::Pawn))
+            | self.piece(Piece::new(side, PieceType::King));
+        self.color_pieces(side) & !pawns_and_king != Bitboard::EMPTY
+    }
+
+    /// Returns whether the position occurred before, given the states saved by the moves that
+    /// led to it. Only positions since the last capture or pawn move, with the same side to
+    /// move, can match.
+    ///
+    /// Neither the positions before the last null move nor the one it produced were ever on the
+    /// board in that order, so the scan stops short of them.
+    pub fn is_repetition(&self, history: &[State]) -> bool {
+        let recent = &history[history.len().saturating_sub(self.half_move_clock as usize)..];
+        let start = recent
+            .iter()
+            .rposition(|state| state.null_move)
+            .map_or(0, |null_move| (null_move + 2).min(recent.len()));
+
+        recent[start..]
+            .iter()
+            .rev()
+            .skip(1)
+            .step_by(2)
+            .any(|state| state.hash == self.hash)
+    }
+
+    /// Restores the state saved before `us` made a move, along with the side to move and
+    /// fullmove number.
+    fn restore_state(&mut self, state: State, us: Color) {
         self.castling_rights = state.castling_rights;
         self.half_move_clock = state.half_move_clock;
         self.en_passant_square = state.en_passant_square;
         self.side_to_move = us;
+        self.hash = state.hash;
+        if !us.is_white() {
+            self.full_move_number -= 1;
+        }
+        debug_assert_eq!(self.hash, self.compute_hash(), "restored hash diverged");
+        debug_assert!(self.is_board_consistent(), "restored mailbox diverged");
     }
 
-    fn remove_rights_for_rook(&mut self, side: Color, rook_sq: Square) {
-        let home_rank = if side.is_white() {
-            Rank::One
-        } else {
-            Rank::Eight
-        };
-        let queen_side_rook_file = File::A;
-        let king_side_rook_file = File::H;
-
-        if rook_sq.rank() == home_rank
-            && (rook_sq.file() == queen_side_rook_file || rook_sq.file() == king_side_rook_file)
-        {
-            let rights_downgrade = if rook_sq.file() == king_side_rook_file {
-                CastlingRights::KingSide
-            } else {
-                CastlingRights::QueenSide
-            };
-            self.castling_rights[side] = self.castling_rights[side].downgrade(rights_downgrade);
+    /// Returns whether the king is not in check and crosses no attacked square on the way to its
+    /// castling destination.
+    ///
+    /// The castling rook is lifted too, so that a rook shielding the king's destination in
+    /// Chess960 does not hide an attack on it.
+    pub(crate) fn is_castling_path_safe(&self, castle: Move) -> bool {
+        let king = castle.from_sq();
+        let (king_destination, _) = castle.castling_squares();
+        let occupied = self.occupied() ^ Bitboard::from(king) ^ castle.to_sq().into();
+        let enemies = self.color_pieces(!self.side_to_move());
+        let mut path = between(king, king_destination) | king.into() | king_destination.into();
+
+        while let Some(square) = path.pop_lsb() {
+            if self.attackers_to(square, occupied) & enemies != Bitboard::EMPTY {
+                return false;
+            }
         }
+
+        true
     }
 
-    /// Returns whether or not a particular square is attacked by a specified side
-    fn is_attacked(&self, target: Square, attacking_side: Color) -> bool {
-        let pawns_bb = self.piece(Piece::new(attacking_side, PieceType::Pawn));
-        if pawns_bb & pawn_attack_mask(!attacking_side, target) != Bitboard::EMPTY {
-            return true;
+    /// Serializes the position into [Forsyth-Edwards Notation](https://www.chessprogramming.org/Forsyth-Edwards_Notation).
+    pub fn to_fen(&self) -> String {
+        let mut fen = String::new();
+        for rank in Rank::values_from(Rank::Eight).rev() {
+            let mut empty_count = 0;
+            for file in File::values() {
+                match self.get_piece_at(&Square::new(file, rank)) {
+                    Some(piece) => {
+                        if empty_count > 0 {
+                            fen.push_str(&empty_count.to_string());
+                            empty_count = 0;
+                        }
+
+                        fen.push_str(&piece.to_string());
+                    }
+                    None => empty_count += 1,
+                }
+            }
+
+            if empty_count > 0 {
+                fen.push_str(&empty_count.to_string());
+            }
+
+            if rank != Rank::One {
+                fen.push(FEN_RANK_SEPARATOR);
+            }
         }
 
-        let knights_bb = self.piece(Piece::new(attacking_side, PieceType::Knight));
-        if knights_bb & attack_mask(PieceType::Knight, target) != Bitboard::EMPTY {
-            return true;
+        let side_to_move = if self.side_to_move.is_white() {
+            "w"
+        } else {
+            "b"
+        };
+
+        let mut castling = String::new();
+        for color in [Color::White, Color::Black] {
+            for (side, letter) in [
+                (CastlingRights::KingSide, 'K'),
+                (CastlingRights::QueenSide, 'Q'),
+            ] {
+                let Some(rook) = self.castling_rook(color, side) else {
+                    continue;
+                };
+
+                // X-FEN names the rook's file only when another rook stands further out
+                let letter = if self.chess960 && self.outermost_rook(color, side) != Some(rook) {
+                    rook.file()
+                        .to_string()
+                        .chars()
+                        .next()
+                        .expect("file has a name")
+                } else {
+                    letter.to_ascii_lowercase()
+                };
+                castling.push(if color.is_white() {
+                    letter.to_ascii_uppercase()
+                } else {
+                    letter
+                });
+            }
         }
 
-        let king_bb = self.piece(Piece::new(attacking_side, PieceType::King));
-        if king_bb & attack_mask(PieceType::King, target) != Bitboard::EMPTY {
-            return true;
+        if castling.is_empty() {
+            castling.push('-');
         }
 
-        let occupied = self.occupied();
-        let bishop_or_queen_bb = self.piece(Piece::new(attacking_side, PieceType::Bishop))
-            | self.piece(Piece::new(attacking_side, PieceType::Queen));
-        if bishop_or_queen_bb & bishop_attacks(target, occupied) != Bitboard::EMPTY {
-            return true;
+        let en_passant = self
+            .en_passant_square
+            .map_or_else(|| "-".to_string(), |square| square.to_string());
+
+        format!(
+            "{fen} {side_to_move} {castling} {en_passant} {} {}",
+            self.half_move_clock, self.full_move_number
+        )
+    }
+
+    fn remove_rights_for_rook(&mut self, side: Color, rook_sq: Square) {
+        for castling_side in [CastlingRights::KingSide, CastlingRights::QueenSide] {
+            if self.castling_rook(side, castling_side) == Some(rook_sq) {
+                self.castling_rights[side] = self.castling_rights[side].downgrade(castling_side);
+            }
         }
+    }
 
-        let rook_or_queen_bb = self.piece(Piece::new(attacking_side, PieceType::Rook))
-            | self.piece(Piece::new(attacking_side, PieceType::Queen));
-        if rook_or_queen_bb & rook_attacks(target, occupied) != Bitboard::EMPTY {
-            return true;
+    /// Returns the rook of `color` on its back rank furthest from the king on `side`.
+    fn outermost_rook(&self, color: Color, side: CastlingRights) -> Option<Square> {
+        outermost_rook(&self.bitboards, color, side)
+    }
+
+    /// Returns whether or not a particular square is attacked by a specified side
+    pub(crate) fn is_attacked(&self, target: Square, attacking_side: Color) -> bool {
+        self.attackers_to(target, self.occupied()) & self.color_pieces(attacking_side)
+            != Bitboard::EMPTY
+    }
+
+    /// Returns the pieces of both sides attacking a square, with sliding attacks blocked by
+    /// `occupied` rather than the current occupancy
+    pub(crate) fn attackers_to(&self, target: Square, occupied: Bitboard) -> Bitboard {
+        let pawns = (self.piece(Piece::new(Color::White, PieceType::Pawn))
+            & pawn_attack_mask(Color::Black, target))
+            | (self.piece(Piece::new(Color::Black, PieceType::Pawn))
+                & pawn_attack_mask(Color::White, target));
+        let knights = self.piece_type(PieceType::Knight) & attack_mask(PieceType::Knight, target);
+        let kings = self.piece_type(PieceType::King) & attack_mask(PieceType::King, target);
+        let queens = self.piece_type(PieceType::Queen);
+        let diagonal =
+            (self.piece_type(PieceType::Bishop) | queens) & bishop_attacks(target, occupied);
+        let orthogonal =
+            (self.piece_type(PieceType::Rook) | queens) & rook_attacks(target, occupied);
+
+        pawns | knights | kings | diagonal | orthogonal
+    }
+
+    /// Fetches the pieces of a given type for both sides
+    fn piece_type(&self, piece_type: PieceType) -> Bitboard {
+        self.piece(Piece::new(Color::White, piece_type))
+            | self.piece(Piece::new(Color::Black, piece_type))
+    }
+
+    /// Places a piece on an empty square, keeping the mailbox, occupancy and hash in sync.
+    fn put_piece(&mut self, piece: Piece, square: Square) {
+        debug_assert_eq!(self.mailbox[square as usize], None, "square is occupied");
+        self.bitboards[piece] ^= square.into();
+        self.occupancy[piece.color() as usize] ^= square.into();
+        self.mailbox[square as usize] = Some(piece);
+        self.hash ^= zobrist::piece_key(piece, square);
+    }
+
+    /// Takes a piece off its square, keeping the mailbox, occupancy and hash in sync.
+    fn remove_piece(&mut self, piece: Piece, square: Square) {
+        debug_assert_eq!(
+            self.mailbox[square as usize],
+            Some(piece),
+            "piece is not on square"
+        );
+        self.bitboards[piece] ^= square.into();
+        self.occupancy[piece.color() as usize] ^= square.into();
+        self.mailbox[square as usize] = None;
+        self.hash ^= zobrist::piece_key(piece, square);
+    }
+
+    /// Returns whether the mailbox and color occupancy agree with the piece bitboards.
+    fn is_board_consistent(&self) -> bool {
+        self.mailbox == build_mailbox(&self.bitboards)
+            && self.occupancy == build_occupancy(&self.bitboards)
+    }
+
+    /// Combined hash keys of the castling rights and en passant square.
+    fn state_key(&self) -> u64 {
+        let mut key = zobrist::castling_key(Color::White, self.castling_rights[Color::White])
+            ^ zobrist::castling_key(Color::Black, self.castling_rights[Color::Black]);
+        if let Some(square) = self.en_passant_square {
+            key ^= zobrist::en_passant_key(square);
         }
 
-        false
+        key
     }
 
-    fn piece_mut(&mut self, piece: Piece) -> &mut Bitboard {
-        &mut self.bitboards[piece]
+    /// Computes the hash of the position from scratch.
+    fn compute_hash(&self) -> u64 {
+        let mut hash = self.state_key();
+        if !self.side_to_move.is_white() {
+            hash ^= zobrist::side_to_move_key();
+        }
+
+        for (i, bb) in self.bitboards.iter().enumerate() {
+            let color = if i < NUM_PIECES {
+                Color::White
+            } else {
+                Color::Black
+            };
+            let piece = Piece::new(
+                color,
+                PieceType::try_from(i % NUM_PIECES).expect("invalid piece index"),
+            );
+            let mut squares = *bb;
+            while let Some(square) = squares.pop_lsb() {
+                hash ^= zobrist::piece_key(piece, square);
+            }
+        }
+
+        hash
     }
 
     fn board_state(&self) -> State {
         State {
+            hash: self.hash,
             castling_rights: self.castling_rights,
             en_passant_square: self.en_passant_square,
             half_move_clock: self.half_move_clock,
             captured_piece: None,
+            null_move: false,
         }
     }
 }
 
-// TODO: Add fullmove clock & impl Display for to FEN
 impl FromStr for Position {
     type Err = Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if !s.is_ascii() {
-            return Err(Error::InvalidFen);
+        if let Some(offset) = s.find(|c: char| !c.is_ascii()) {
+            return Err(fen_error(FenField::Record, offset, FenError::NonAscii));
         }
 
-        let components: Vec<&str> = s.split_whitespace().collect();
-        if components.len() != 6 {
-            return Err(Error::InvalidFen);
+        // Pair every field with its offset into the record so errors can point at it
+        let fields: Vec<(usize, &str)> = s
+            .split_whitespace()
+            .map(|field| (field.as_ptr() as usize - s.as_ptr() as usize, field))
+            .collect();
+        if fields.len() != 6 {
+            return Err(fen_error(
+                FenField::Record,
+                0,
+                FenError::FieldCount(fields.len()),
+            ));
         }
 
-        let bitboards = parse_fen_board(components[0])?;
-        let side_to_move: Color = components[1].parse()?;
-        let castling_rights = if components[2] == "-" {
-            [CastlingRights::None; NUM_COLORS]
-        } else {
-            let rights_str = components[2];
-            let split_index = rights_str
-                .char_indices()
-                .find(|(_, c)| c.is_lowercase())
-                .map(|(i, _)| i)
-                .unwrap_or(rights_str.len());
-            let (white_rights, black_rights) = rights_str.split_at(split_index);
-
-            [white_rights.parse()?, black_rights.parse()?]
-        };
-
-        // TODO: Add validation checks of the en passant square and board
-        let en_passant_square: Option<Square> = match components[3] {
-            "-" => None,
-            _ => Some(components[3].parse().map_err(|_| Error::InvalidFen)?),
-        };
-
-        let half_move_clock: u8 = components[4].parse().map_err(|_| Error::InvalidFen)?;
+        let (board_offset, board) = fields[0];
+        let bitboards = parse_fen_board(board, board_offset)?;
+        validate_fen_board(&bitboards, board_offset)?;
+
+        let (side_offset, side) = fields[1];
+        let side_to_move: Color = side.parse().map_err(|_| {
+            fen_error(
+                FenField::SideToMove,
+                side_offset,
+                FenError::InvalidSideToMove(side.to_string()),
+            )
+        })?;
+
+        let (castling_offset, castling) = fields[2];
+        let FenCastling {
+            rights: castling_rights,
+            rook_files: castling_rook_files,
+            chess960,
+        } = parse_fen_castling(castling, castling_offset, &bitboards)?;
+
+        let (en_passant_offset, en_passant) = fields[3];
+        let en_passant_square =
+            parse_fen_en_passant(en_passant, en_passant_offset, side_to_move, &bitboards)?;
+
+        let (half_move_offset, half_move) = fields[4];
+        let half_move_clock: u8 = half_move.parse().map_err(|_| {
+            fen_error(
+                FenField::HalfMoveClock,
+                half_move_offset,
+                FenError::InvalidNumber(half_move.to_string()),
+            )
+        })?;
+        if en_passant != "-" && half_move_clock != 0 {
+            return Err(fen_error(
+                FenField::HalfMoveClock,
+                half_move_offset,
+                FenError::EnPassantClock,
+            ));
+        }
 
-        // The full move clock is not needed for basic position parsing, but still good to check
-        let _: usize = components[5].parse().map_err(|_| Error::InvalidFen)?;
+        let (full_move_offset, full_move) = fields[5];
+        let full_move_number: u16 = full_move.parse().map_err(|_| {
+            fen_error(
+                FenField::FullMoveNumber,
+                full_move_offset,
+                FenError::InvalidNumber(full_move.to_string()),
+            )
+        })?;
+        if full_move_number == 0 {
+            return Err(fen_error(
+                FenField::FullMoveNumber,
+                full_move_offset,
+                FenError::FullMoveZero,
+            ));
+        }
 
-        Ok(Self {
+        let mut position = Self {
             bitboards,
+            mailbox: build_mailbox(&bitboards),
+            occupancy: build_occupancy(&bitboards),
             side_to_move,
             castling_rights,
+            castling_rook_files,
+            chess960,
             en_passant_square,
             half_move_clock,
-        })
+            full_move_number,
+            hash: 0,
+        };
+        position.hash = position.compute_hash();
+
+        // The side that just moved can never have left its king in check
+        if position.is_checked(!side_to_move) {
+            return Err(fen_error(
+                FenField::SideToMove,
+                side_offset,
+                FenError::OpponentInCheck,
+            ));
+        }
+
+        Ok(position)
     }
 }
 
@@ -520,40 +1130,337 @@ impl Default for Position {
     }
 }
 
-fn parse_fen_board(fen_board: &str) -> Result<[Bitboard; NUM_BITBOARDS], Error> {
-    let piece_placement: Vec<&str> = fen_board.split(FEN_RANK_SEPARATOR).collect();
-    if piece_placement.len() != NUM_RANKS {
-        return Err(Error::InvalidFen);
+/// Builds the piece on each square from the piece bitboards.
+fn build_mailbox(bitboards: &[Bitboard; NUM_BITBOARDS]) -> [Option<Piece>; NUM_SQUARES] {
+    let mut mailbox = [None; NUM_SQUARES];
+    for (i, bb) in bitboards.iter().enumerate() {
+        let color = if i < NUM_PIECES {
+            Color::White
+        } else {
+            Color::Black
+        };
+        let piece = Piece::new(
+            color,
+            PieceType::try_from(i % NUM_PIECES).expect("invalid piece index"),
+        );
+        let mut squares = *bb;
+        while let Some(square) = squares.pop_lsb() {
+            mailbox[square as usize] = Some(piece);
+        }
+    }
+
+    mailbox
+}
+
+/// Builds the occupancy of each color from the piece bitboards.
+fn build_occupancy(bitboards: &[Bitboard; NUM_BITBOARDS]) -> [Bitboard; NUM_COLORS] {
+    let (white, black) = bitboards.split_at(NUM_PIECES);
+    let combine = |bbs: &[Bitboard]| bbs.iter().fold(Bitboard::EMPTY, |acc, bb| acc | *bb);
+    [combine(white), combine(black)]
+}
+
+fn fen_error(field: FenField, offset: usize, reason: FenError) -> Error {
+    Error::InvalidFen {
+        field,
+        offset,
+        reason,
+    }
+}
+
+fn parse_fen_board(fen_board: &str, offset: usize) -> Result<[Bitboard; NUM_BITBOARDS], Error> {
+    let error = |index: usize, reason| fen_error(FenField::PiecePlacement, offset + index, reason);
+    let rank_count = fen_board.split(FEN_RANK_SEPARATOR).count();
+    if rank_count != NUM_RANKS {
+        return Err(error(0, FenError::RankCount(rank_count)));
     }
 
     let mut bitboards = [Bitboard::EMPTY; NUM_BITBOARDS];
-    let mut rank_opt = Some(Rank::Eight);
-    for &rank_str in piece_placement.iter() {
-        let rank = rank_opt.ok_or(Error::InvalidFen)?;
-        let mut file_opt = Some(File::A);
-        for c in rank_str.chars() {
-            let file = file_opt.ok_or(Error::InvalidFen)?;
-            if c.is_numeric() {
-                let count = c.to_digit(10).ok_or(Error::ParseError)?;
-                file_opt = file.right_n(count as u8);
-            } else if c.is_alphabetic() {
-                let piece: Piece = c.to_string().parse()?;
-                bitboards[piece] |= Bitboard(1 << Square::new(file, rank) as u64);
-                file_opt = file.right_n(1);
-            } else {
-                return Err(Error::InvalidFen);
+    let mut rank = NUM_RANKS - 1;
+    let mut file = 0;
+    let mut after_digit = false;
+    for (i, c) in fen_board.char_indices() {
+        match c {
+            FEN_RANK_SEPARATOR => {
+                if file != NUM_FILES {
+                    return Err(error(
+                        i,
+                        FenError::RankSize {
+                            rank: rank + 1,
+                            squares: file,
+                        },
+                    ));
+                }
+
+                rank -= 1;
+                file = 0;
+                after_digit = false;
+                continue;
             }
+            '1'..='8' if after_digit => return Err(error(i, FenError::ConsecutiveDigits)),
+            '1'..='8' => {
+                file += c as usize - '0' as usize;
+                after_digit = true;
+            }
+            _ if c.is_ascii_digit() => return Err(error(i, FenError::InvalidDigit(c))),
+            _ => {
+                let piece: Piece = c
+                    .to_string()
+                    .parse()
+                    .map_err(|_| error(i, FenError::InvalidPiece(c)))?;
+                if file < NUM_FILES {
+                    let square = Square::from_lsf_index((rank * NUM_FILES + file) as u8)?;
+                    bitboards[piece] |= square.into();
+                }
+
+                file += 1;
+                after_digit = false;
+            }
+        }
+
+        if file > NUM_FILES {
+            return Err(error(
+                i,
+                FenError::RankSize {
+                    rank: rank + 1,
+                    squares: file,
+                },
+            ));
         }
+    }
 
-        rank_opt = rank.down_n(1);
+    if file != NUM_FILES {
+        return Err(error(
+            fen_board.len(),
+            FenError::RankSize {
+                rank: rank + 1,
+                squares: file,
+            },
+        ));
     }
 
     Ok(bitboards)
 }
 
+/// Checks that the piece placement could occur in a real game.
+fn validate_fen_board(bitboards: &[Bitboard; NUM_BITBOARDS], offset: usize) -> Result<(), Error> {
+    let error = |reason| fen_error(FenField::PiecePlacement, offset, reason);
+    let back_ranks = Bitboard::from(Rank::One) | Bitboard::from(Rank::Eight);
+    for color in [Color::White, Color::Black] {
+        let count = bitboards[Piece::new(color, PieceType::King)].count();
+        if count != 1 {
+            return Err(error(FenError::KingCount { color, count }));
+        }
+
+        let pawns = bitboards[Piece::new(color, PieceType::Pawn)];
+        if pawns.count() > NUM_FILES as u32 {
+            return Err(error(FenError::PawnCount {
+                color,
+                count: pawns.count(),
+            }));
+        }
+
+        if let Some(square) = (pawns & back_ranks).lsb() {
+            return Err(error(FenError::PawnOnBackRank(square)));
+        }
+    }
+
+    Ok(())
+}
+
+/// The castling setup described by a FEN castling field.
+struct FenCastling {
+    rights: [CastlingRights; NUM_COLORS],
+    rook_files: [[File; 2]; NUM_COLORS],
+    /// Whether a king or castling rook is off its standard square.
+    chess960: bool,
+}
+
+/// Parses a castling field in the standard `KQkq` form, or in the X-FEN and Shredder-FEN forms
+/// that name the rook's file.
+///
+/// `K` and `Q` stand for the outermost rook on either side of the king.
+fn parse_fen_castling(
+    castling: &str,
+    offset: usize,
+    bitboards: &[Bitboard; NUM_BITBOARDS],
+) -> Result<FenCastling, Error> {
+    let mut rights = [CastlingRights::None; NUM_COLORS];
+    let mut rook_files = [[File::H, File::A]; NUM_COLORS];
+    if castling == "-" {
+        return Ok(FenCastling {
+            rights,
+            rook_files,
+            chess960: false,
+        });
+    }
+
+    let mut chess960 = false;
+    let mut next_allowed = 0;
+    for (i, c) in castling.char_indices() {
+        let error = |reason| fen_error(FenField::Castling, offset + i, reason);
+        let color = if c.is_ascii_uppercase() {
+            Color::White
+        } else {
+            Color::Black
+        };
+        let home_rank = if color.is_white() {
+            Rank::One
+        } else {
+            Rank::Eight
+        };
+        let king = (bitboards[Piece::new(color, PieceType::King)] & home_rank.into()).lsb();
+        let rooks = bitboards[Piece::new(color, PieceType::Rook)] & home_rank.into();
+
+        let rook = match c.to_ascii_lowercase() {
+            'k' => king.and_then(|_| outermost_rook(bitboards, color, CastlingRights::KingSide)),
+            'q' => king.and_then(|_| outermost_rook(bitboards, color, CastlingRights::QueenSide)),
+            'a'..='h' => {
+                let file = File::from_str(&c.to_ascii_lowercase().to_string())
+                    .map_err(|_| error(FenError::InvalidCastlingChar(c)))?;
+                let square = Square::new(file, home_rank);
+                (rooks & square.into() != Bitboard::EMPTY).then_some(square)
+            }
+            _ => return Err(error(FenError::InvalidCastlingChar(c))),
+        };
+        let (Some(king), Some(rook)) = (king, rook) else {
+            return Err(error(FenError::CastlingWithoutPieces(c)));
+        };
+
+        // White's rights come before black's, and each color's king side before its queen side
+        let king_side = rook.file().distance(&king.file()) > 0;
+        let order = color as usize * 2 + usize::from(!king_side);
+        if order < next_allowed {
+            return Err(error(FenError::CastlingOrder(c)));
+        }
+        next_allowed = order + 1;
+
+        let (side, home_file) = if king_side {
+            (CastlingRights::KingSide, File::H)
+        } else {
+            (CastlingRights::QueenSide, File::A)
+        };
+        rights[color] = rights[color].union(side);
+        rook_files[color as usize][usize::from(!king_side)] = rook.file();
+        chess960 |= king.file() != File::E || rook.file() != home_file;
+    }
+
+    Ok(FenCastling {
+        rights,
+        rook_files,
+        chess960,
+    })
+}
+
+/// Returns the rook of `color` on its back rank furthest from its king on `side`.
+fn outermost_rook(
+    bitboards: &[Bitboard; NUM_BITBOARDS],
+    color: Color,
+    side: CastlingRights,
+) -> Option<Square> {
+    let home_rank = if color.is_white() {
+        Rank::One
+    } else {
+        Rank::Eight
+    };
+    let king = (bitboards[Piece::new(color, PieceType::King)] & home_rank.into()).lsb()?;
+    let rooks = bitboards[Piece::new(color, PieceType::Rook)] & home_rank.into();
+    let files_beyond_king = if side == CastlingRights::KingSide {
+        Bitboard(
+            u64::MAX
+                .checked_shl(king.lsf_index() as u32 + 1)
+                .unwrap_or(0),
+        )
+    } else {
+        Bitboard((1 << king.lsf_index()) - 1)
+    };
+
+    let candidates = rooks & files_beyond_king;
+    if side == CastlingRights::KingSide {
+        candidates.msb()
+    } else {
+        candidates.lsb()
+    }
+}
+
+fn parse_fen_en_passant(
+    en_passant: &str,
+    offset: usize,
+    side_to_move: Color,
+    bitboards: &[Bitboard; NUM_BITBOARDS],
+) -> Result<Option<Square>, Error> {
+    if en_passant == "-" {
+        return Ok(None);
+    }
+
+    let error = |reason| fen_error(FenField::EnPassant, offset, reason);
+    let square: Square = en_passant
+        .parse()
+        .map_err(|_| error(FenError::InvalidSquare(en_passant.to_string())))?;
+
+    // The target is the square skipped by the opponent's double push
+    let (target_rank, pushed_pawn, pawn_origin) = if side_to_move.is_white() {
+        (Rank::Six, square.south(), square.north())
+    } else {
+        (Rank::Three, square.north(), square.south())
+    };
+    if square.rank() != target_rank {
+        return Err(error(FenError::EnPassantRank(square)));
+    }
+
+    let (Some(pushed_pawn), Some(pawn_origin)) = (pushed_pawn, pawn_origin) else {
+        return Err(error(FenError::EnPassantRank(square)));
+    };
+
+    let occupied = bitboards.iter().fold(Bitboard::EMPTY, |acc, bb| acc | *bb);
+    let enemy_pawns = bitboards[Piece::new(!side_to_move, PieceType::Pawn)];
+    if enemy_pawns & pushed_pawn.into() == Bitboard::EMPTY
+        || occupied & (Bitboard::from(square) | Bitboard::from(pawn_origin)) != Bitboard::EMPTY
+    {
+        return Err(error(FenError::EnPassantWithoutPawn(square)));
+    }
+
+    // Like `make_move`, only keep the square when a pawn beside the pushed one can capture, so
+    // the hash matches that of the same position reached by moves
+    let our_pawns = bitboards[Piece::new(side_to_move, PieceType::Pawn)];
+    let capturers = [pushed_pawn.east(), pushed_pawn.west()]
+        .into_iter()
+        .flatten()
+        .fold(Bitboard::EMPTY, |acc, square| acc | square.into());
+    if our_pawns & capturers == Bitboard::EMPTY {
+        return Ok(None);
+    }
+
+    Ok(Some(square))
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::chess::position::*;
+    use crate::chess::{
+        movegen::{MoveList, generate_moves},
+        position::*,
+    };
+
+    /// Checks that the incrementally updated hash, mailbox and occupancy match ones rebuilt from
+    /// scratch, and that unmaking every move restores the position exactly.
+    fn verify_incremental_state(position: &mut Position, history: &mut Vec<State>, depth: u32) {
+        assert_eq!(position.compute_hash(), position.hash());
+        assert!(position.is_board_consistent());
+        if depth == 0 {
+            return;
+        }
+
+        let mut moves = MoveList::new();
+        generate_moves(position, &mut moves);
+        for mv in moves {
+            let before = position.clone();
+            if position.make_move(mv, history) {
+                verify_incremental_state(position, history, depth - 1);
+                position.unmake_move(mv, history);
+            }
+
+            assert_eq!(before, *position, "{mv:?}");
+        }
+    }
 
     #[test]
     fn test_starting_parse() {
@@ -583,7 +1490,7 @@ mod tests {
 
     #[test]
     fn test_midgame_parse() {
-        let mid_game_fen = "rnbqkbnr/pp1ppppp/8/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq e4 1 2";
+        let mid_game_fen = "rnbqkbnr/pp1ppppp/8/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq e3 0 2";
         let p: Position = mid_game_fen.parse().unwrap();
 
         let expected_bb: [Bitboard; NUM_BITBOARDS] = [
@@ -602,15 +1509,16 @@ mod tests {
         ];
 
         assert_eq!(Color::Black, p.side_to_move);
-        assert_eq!(1, p.half_move_clock);
-        assert_eq!(Some(Square::E4), p.en_passant_square);
+        assert_eq!(0, p.half_move_clock);
+        // No black pawn can capture on e3
+        assert_eq!(None, p.en_passant_square);
         assert_eq!([CastlingRights::All; NUM_COLORS], p.castling_rights);
         assert_eq!(expected_bb, p.bitboards);
     }
 
     #[test]
     fn test_endgame_parse() {
-        let mid_game_fen = "5k2/ppp5/4P3/3R3p/6P1/1K2Nr3/PP3P2/8 b - - 1 32";
+        let mid_game_fen = "5k2/ppp5/4P3/3R3p/6P1/1K2Nr2/PP3P2/8 b - - 1 32";
         let p: Position = mid_game_fen.parse().unwrap();
 
         let expected_bb: [Bitboard; NUM_BITBOARDS] = [
@@ -634,4 +1542,564 @@ mod tests {
         assert_eq!([CastlingRights::None; NUM_COLORS], p.castling_rights);
         assert_eq!(expected_bb, p.bitboards);
     }
+
+    #[test]
+    fn test_see() {
+        for (fen, from, to, kind, expected) in [
+            // Undefended pawn
+            (
+                "1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1",
+                Square::E1,
+                Square::E5,
+                MoveKind::Capture,
+                100,
+            ),
+            // Pawn defended by a pawn
+            (
+                "4k3/8/4p3/3p4/8/8/8/3QK3 w - - 0 1",
+                Square::D1,
+                Square::D5,
+                MoveKind::Capture,
+                -800,
+            ),
+            // The rook behind the capturing rook deters the recapture
+            (
+                "3rk3/8/8/3p4/8/8/3R4/3RK3 w - - 0 1",
+                Square::D2,
+                Square::D5,
+                MoveKind::Capture,
+                100,
+            ),
+            // A defended rook cannot be taken by the king
+            (
+                "8/8/4k3/3p4/8/8/8/K2R3B w - - 0 1",
+                Square::D1,
+                Square::D5,
+                MoveKind::Capture,
+                100,
+            ),
+            (
+                "8/8/4k3/3p4/8/8/8/K2R4 w - - 0 1",
+                Square::D1,
+                Square::D5,
+                MoveKind::Capture,
+                -400,
+            ),
+            // Quiet moves only lose material
+            (
+                "4k3/8/4p3/8/8/8/8/3QK3 w - - 0 1",
+                Square::D1,
+                Square::D5,
+                MoveKind::Quiet,
+                -900,
+            ),
+            (
+                "4k3/8/8/8/8/8/8/3QK3 w - - 0 1",
+                Square::D1,
+                Square::D5,
+                MoveKind::Quiet,
+                0,
+            ),
+            (
+                "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1",
+                Square::E5,
+                Square::D6,
+                MoveKind::EnPassant,
+                100,
+            ),
+            (
+                "4k3/P7/8/8/8/8/8/4K3 w - - 0 1",
+                Square::A7,
+                Square::A8,
+                MoveKind::Promotion(PieceType::Queen),
+                800,
+            ),
+        ] {
+            let position: Position = fen.parse().unwrap();
+            let mv = Move::new(from, to, kind);
+            assert_eq!(expected, position.see(mv), "{fen}");
+            assert!(position.see_ge(mv, expected), "{fen}");
+            assert!(!position.see_ge(mv, expected + 1), "{fen}");
+        }
+    }
+
+    #[test]
+    fn test_see_ge_matches_see() {
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            "1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1",
+        ] {
+            let position: Position = fen.parse().unwrap();
+            let mut moves = MoveList::new();
+            generate_moves(&position, &mut moves);
+            for mv in moves {
+                let see = position.see(mv);
+                for threshold in (-1000..=1000).step_by(10) {
+                    assert_eq!(
+                        see >= threshold,
+                        position.see_ge(mv, threshold),
+                        "{fen} {mv:?} {threshold}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_incremental_state_matches_recomputation() {
+        for fen in [
+            STARTING_FEN,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "1rqbkrbn/1ppppp1p/1n6/p1N3p1/8/2P4P/PP1PPPP1/1RQBKRBN w FBfb - 0 9",
+        ] {
+            let mut position: Position = fen.parse().unwrap();
+            verify_incremental_state(&mut position, &mut Vec::new(), 2);
+        }
+    }
+
+    #[test]
+    fn test_hash_transposition() {
+        let mut history = Vec::new();
+        let mut position = Position::default();
+        for mv in [
+            Move::new(Square::G1, Square::F3, MoveKind::Quiet),
+            Move::new(Square::G8, Square::F6, MoveKind::Quiet),
+            Move::new(Square::B1, Square::C3, MoveKind::Quiet),
+        ] {
+            assert!(position.make_move(mv, &mut history));
+        }
+
+        let mut transposed = Position::default();
+        for mv in [
+            Move::new(Square::B1, Square::C3, MoveKind::Quiet),
+            Move::new(Square::G8, Square::F6, MoveKind::Quiet),
+            Move::new(Square::G1, Square::F3, MoveKind::Quiet),
+        ] {
+            assert!(transposed.make_move(mv, &mut history));
+        }
+
+        assert_eq!(position.hash(), transposed.hash());
+        assert_ne!(Position::default().hash(), position.hash());
+    }
+
+    #[test]
+    fn test_hash_includes_side_and_castling() {
+        let white: Position = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1".parse().unwrap();
+        let black: Position = "r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1".parse().unwrap();
+        let no_castling: Position = "r3k2r/8/8/8/8/8/8/R3K2R w Kkq - 0 1".parse().unwrap();
+
+        assert_ne!(white.hash(), black.hash());
+        assert_ne!(white.hash(), no_castling.hash());
+    }
+
+    #[test]
+    fn test_null_move() {
+        let fen = "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3";
+        let mut position = Position::from_str(fen).unwrap();
+        let original = position.clone();
+        let mut history = Vec::new();
+
+        position.make_null_move(&mut history);
+        assert_eq!(Color::Black, position.side_to_move());
+        assert_eq!(None, position.en_passant_square());
+        assert_eq!(
+            Position::from_str("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR b KQkq - 1 3")
+                .unwrap()
+                .hash(),
+            position.hash()
+        );
+
+        position.unmake_null_move(&mut history);
+        assert_eq!(original, position);
+        assert!(history.is_empty());
+    }
+
+    #[test]
+    fn test_has_non_pawn_material() {
+        let position = Position::from_str("4k3/pppp4/8/8/8/8/4P3/4KN2 w - - 0 1").unwrap();
+        assert!(position.has_non_pawn_material(Color::White));
+        assert!(!position.has_non_pawn_material(Color::Black));
+    }
+
+    #[test]
+    fn test_is_repetition() {
+        let mut position = Position::default();
+        let mut history = Vec::new();
+        for uci in ["g1f3", "g8f6", "f3g1"] {
+            let mv = Move::from_uci(uci, &position).unwrap();
+            assert!(position.make_move(mv, &mut history));
+            assert!(!position.is_repetition(&history));
+        }
+
+        let mv = Move::from_uci("f6g8", &position).unwrap();
+        assert!(position.make_move(mv, &mut history));
+        assert!(position.is_repetition(&history));
+
+        // A pawn move resets the half move clock, so earlier positions can no longer repeat
+        let mv = Move::from_uci("e2e4", &position).unwrap();
+        assert!(position.make_move(mv, &mut history));
+        assert!(!position.is_repetition(&history));
+    }
+
+    #[test]
+    fn test_is_repetition_stops_at_null_move() {
+        let mut position = Position::default();
+        let mut history = Vec::new();
+        position.make_null_move(&mut history);
+        for uci in ["g8f6", "g1f3", "f6g8", "f3g1"] {
+            let mv = Move::from_uci(uci, &position).unwrap();
+            assert!(position.make_move(mv, &mut history));
+            assert!(!position.is_repetition(&history));
+        }
+
+        // Positions after the null move still repeat
+        let mv = Move::from_uci("g8f6", &position).unwrap();
+        assert!(position.make_move(mv, &mut history));
+        assert!(position.is_repetition(&history));
+    }
+
+    #[test]
+    fn test_to_fen() {
+        for fen in [
+            STARTING_FEN,
+            "rnbqkbnr/pp1ppppp/8/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2",
+            "5k2/ppp5/4P3/3R3p/6P1/1K2Nr2/PP3P2/8 b - - 1 32",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w Kq f6 0 3",
+        ] {
+            let p: Position = fen.parse().unwrap();
+            assert_eq!(fen, p.to_fen());
+        }
+    }
+
+    #[test]
+    fn test_full_move_number() {
+        let mut history = Vec::new();
+        let mut p = Position::default();
+        let e4 = Move::new(Square::E2, Square::E4, MoveKind::Quiet);
+        let e5 = Move::new(Square::E7, Square::E5, MoveKind::Quiet);
+
+        assert!(p.make_move(e4, &mut history));
+        assert_eq!(1, p.full_move_number());
+        assert!(p.make_move(e5, &mut history));
+        assert_eq!(2, p.full_move_number());
+        assert_eq!(
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2",
+            p.to_fen()
+        );
+
+        p.unmake_move(e5, &mut history);
+        assert_eq!(1, p.full_move_number());
+        p.unmake_move(e4, &mut history);
+        assert_eq!(Position::default(), p);
+    }
+
+    /// Asserts that parsing `fen` fails in `field` at `offset` because of `reason`.
+    fn assert_fen_error(fen: &str, field: FenField, offset: usize, reason: FenError) {
+        match fen.parse::<Position>() {
+            Err(Error::InvalidFen {
+                field: actual_field,
+                offset: actual_offset,
+                reason: actual_reason,
+            }) => {
+                assert_eq!(
+                    (field, offset, reason),
+                    (actual_field, actual_offset, actual_reason),
+                    "{fen}"
+                );
+            }
+            result => panic!("expected FEN error for '{fen}', got {result:?}"),
+        }
+    }
+
+    #[test]
+    fn test_fen_record_errors() {
+        assert_fen_error(
+            "8/8/8/8/8/8/8/8 w - - 0",
+            FenField::Record,
+            0,
+            FenError::FieldCount(5),
+        );
+        assert_fen_error(
+            "4k3/8/8/8/8/8/8/4K3 w - - 0 1 é",
+            FenField::Record,
+            30,
+            FenError::NonAscii,
+        );
+    }
+
+    #[test]
+    fn test_fen_board_errors() {
+        assert_fen_error(
+            "4k3/8/8/8/8/8/4K3 w - - 0 1",
+            FenField::PiecePlacement,
+            0,
+            FenError::RankCount(7),
+        );
+        assert_fen_error(
+            "4k3/8/8/8/7/8/8/4K3 w - - 0 1",
+            FenField::PiecePlacement,
+            11,
+            FenError::RankSize {
+                rank: 4,
+                squares: 7,
+            },
+        );
+        assert_fen_error(
+            "4k3/8/8/8/8/8/8/4K4 w - - 0 1",
+            FenField::PiecePlacement,
+            18,
+            FenError::RankSize {
+                rank: 1,
+                squares: 9,
+            },
+        );
+        assert_fen_error(
+            "4k3/8/8/8/8/8/8/4K3p w - - 0 1",
+            FenField::PiecePlacement,
+            19,
+            FenError::RankSize {
+                rank: 1,
+                squares: 9,
+            },
+        );
+        assert_fen_error(
+            "4k3/9/8/8/8/8/8/4K3 w - - 0 1",
+            FenField::PiecePlacement,
+            4,
+            FenError::InvalidDigit('9'),
+        );
+        assert_fen_error(
+            "4k3/44/8/8/8/8/8/4K3 w - - 0 1",
+            FenField::PiecePlacement,
+            5,
+            FenError::ConsecutiveDigits,
+        );
+        assert_fen_error(
+            "4k3/8/8/3x4/8/8/8/4K3 w - - 0 1",
+            FenField::PiecePlacement,
+            9,
+            FenError::InvalidPiece('x'),
+        );
+    }
+
+    #[test]
+    fn test_fen_material_errors() {
+        assert_fen_error(
+            "8/8/8/8/8/8/8/4K3 w - - 0 1",
+            FenField::PiecePlacement,
+            0,
+            FenError::KingCount {
+                color: Color::Black,
+                count: 0,
+            },
+        );
+        assert_fen_error(
+            "4k3/8/8/8/8/8/8/3KK3 w - - 0 1",
+            FenField::PiecePlacement,
+            0,
+            FenError::KingCount {
+                color: Color::White,
+                count: 2,
+            },
+        );
+        assert_fen_error(
+            "4k3/8/8/8/8/P7/PPPPPPPP/4K3 w - - 0 1",
+            FenField::PiecePlacement,
+            0,
+            FenError::PawnCount {
+                color: Color::White,
+                count: 9,
+            },
+        );
+        assert_fen_error(
+            "4k2p/8/8/8/8/8/8/4K3 w - - 0 1",
+            FenField::PiecePlacement,
+            0,
+            FenError::PawnOnBackRank(Square::H8),
+        );
+        assert_fen_error(
+            "4k2R/8/8/8/8/8/8/4K3 w - - 0 1",
+            FenField::SideToMove,
+            21,
+            FenError::OpponentInCheck,
+        );
+    }
+
+    #[test]
+    fn test_fen_side_to_move_errors() {
+        assert_fen_error(
+            "4k3/8/8/8/8/8/8/4K3 x - - 0 1",
+            FenField::SideToMove,
+            20,
+            FenError::InvalidSideToMove("x".to_string()),
+        );
+    }
+
+    #[test]
+    fn test_chess960_start_positions() {
+        for (index, back_rank) in [(0, "bbqnnrkr"), (518, "rnbqkbnr"), (959, "rkrnnqbb")] {
+            let p = Position::from_chess960_index(index).unwrap();
+            let fen = format!(
+                "{back_rank}/pppppppp/8/8/8/8/PPPPPPPP/{} w KQkq - 0 1",
+                back_rank.to_uppercase()
+            );
+
+            assert!(p.is_chess960());
+            assert_eq!(fen, p.to_fen());
+        }
+
+        assert!(matches!(
+            Position::from_chess960_index(960),
+            Err(Error::InvalidIndex)
+        ));
+    }
+
+    #[test]
+    fn test_chess960_castling_fields() {
+        let standard = Position::from_str("r3k2r/8/8/8/8/8/8/R3K2R w HAha - 0 1").unwrap();
+        assert!(!standard.is_chess960());
+        assert_eq!("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", standard.to_fen());
+
+        let shredder = Position::from_str("1r2k1r1/8/8/8/8/8/8/1R2K1R1 w GBgb - 0 1").unwrap();
+        assert!(shredder.is_chess960());
+        assert_eq!(
+            Some(Square::G1),
+            shredder.castling_rook(Color::White, CastlingRights::KingSide)
+        );
+        assert_eq!(
+            Some(Square::B8),
+            shredder.castling_rook(Color::Black, CastlingRights::QueenSide)
+        );
+        assert_eq!(
+            None,
+            shredder.castling_rook(Color::White, CastlingRights::All)
+        );
+        assert_eq!(
+            None,
+            shredder.castling_rook(Color::White, CastlingRights::None)
+        );
+        assert_eq!(
+            "1r2k1r1/8/8/8/8/8/8/1R2K1R1 w KQkq - 0 1",
+            shredder.to_fen()
+        );
+
+        // An inner rook is named by its file, as K and Q stand for the outermost one
+        let inner_rook = Position::from_str("4k3/8/8/8/8/8/8/R2RK3 w D - 0 1").unwrap();
+        assert!(inner_rook.is_chess960());
+        assert_eq!(
+            Some(Square::D1),
+            inner_rook.castling_rook(Color::White, CastlingRights::QueenSide)
+        );
+        assert_eq!("4k3/8/8/8/8/8/8/R2RK3 w D - 0 1", inner_rook.to_fen());
+    }
+
+    #[test]
+    fn test_fen_castling_errors() {
+        assert_fen_error(
+            "r3k2r/8/8/8/8/8/8/R3K2R w KQkx - 0 1",
+            FenField::Castling,
+            29,
+            FenError::InvalidCastlingChar('x'),
+        );
+        assert_fen_error(
+            "r3k2r/8/8/8/8/8/8/R3K2R w QK - 0 1",
+            FenField::Castling,
+            27,
+            FenError::CastlingOrder('K'),
+        );
+        assert_fen_error(
+            "r3k2r/8/8/8/8/8/8/R3K2R w KK - 0 1",
+            FenField::Castling,
+            27,
+            FenError::CastlingOrder('K'),
+        );
+        assert_fen_error(
+            "r3k2r/8/8/8/8/8/8/R3K3 w Kkq - 0 1",
+            FenField::Castling,
+            25,
+            FenError::CastlingWithoutPieces('K'),
+        );
+        assert_fen_error(
+            "3k3r/8/8/8/8/8/8/R3K2R w KQq - 0 1",
+            FenField::Castling,
+            27,
+            FenError::CastlingWithoutPieces('q'),
+        );
+    }
+
+    #[test]
+    fn test_fen_en_passant_errors() {
+        assert_fen_error(
+            "4k3/8/8/8/8/8/8/4K3 w - z9 0 1",
+            FenField::EnPassant,
+            24,
+            FenError::InvalidSquare("z9".to_string()),
+        );
+        assert_fen_error(
+            "4k3/8/8/3pP3/8/8/8/4K3 w - d3 0 1",
+            FenField::EnPassant,
+            27,
+            FenError::EnPassantRank(Square::D3),
+        );
+        assert_fen_error(
+            "4k3/8/8/4P3/8/8/8/4K3 w - d6 0 1",
+            FenField::EnPassant,
+            26,
+            FenError::EnPassantWithoutPawn(Square::D6),
+        );
+        assert_fen_error(
+            "4k3/3p4/8/3pP3/8/8/8/4K3 w - d6 0 1",
+            FenField::EnPassant,
+            29,
+            FenError::EnPassantWithoutPawn(Square::D6),
+        );
+        assert_fen_error(
+            "4k3/8/8/3pP3/8/8/8/4K3 w - d6 1 2",
+            FenField::HalfMoveClock,
+            30,
+            FenError::EnPassantClock,
+        );
+    }
+
+    #[test]
+    fn test_fen_en_passant_without_capture() {
+        let p: Position = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"
+            .parse()
+            .unwrap();
+        assert_eq!(None, p.en_passant_square());
+
+        let mut after_e4 = Position::default();
+        let e4 = Move::new(Square::E2, Square::E4, MoveKind::Quiet);
+        assert!(after_e4.make_move(e4, &mut Vec::new()));
+        assert_eq!(after_e4.hash(), p.hash());
+
+        // The field must still be valid to be dropped
+        assert_fen_error(
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 1 1",
+            FenField::HalfMoveClock,
+            56,
+            FenError::EnPassantClock,
+        );
+    }
+
+    #[test]
+    fn test_fen_counter_errors() {
+        assert_fen_error(
+            "4k3/8/8/8/8/8/8/4K3 w - - -1 1",
+            FenField::HalfMoveClock,
+            26,
+            FenError::InvalidNumber("-1".to_string()),
+        );
+        assert_fen_error(
+            "4k3/8/8/8/8/8/8/4K3 w - - 0 0",
+            FenField::FullMoveNumber,
+            28,
+            FenError::FullMoveZero,
+        );
+    }
 }
@@ -0,0 +1,439 @@
+use crate::chess::{
+    Error, File, PieceType, Position, Rank, SanError, Square,
+    movegen::{Move, MoveKind, generate_legal_moves},
+};
+
+/// Characters that may trail a SAN move without changing its meaning.
+const SAN_SUFFIXES: &[char] = &['+', '#', '!', '?'];
+
+impl Move {
+    /// Formats the move in [Standard Algebraic Notation](https://www.chessprogramming.org/Algebraic_Chess_Notation#SAN)
+    /// for the position it is played in.
+    ///
+    /// The move must be legal in `position`.
+    pub fn to_san(&self, position: &Position) -> String {
+        let from = self.from_sq();
+        let to = self.to_sq();
+        let mut san = match self.kind() {
+            MoveKind::Castle if to.file().distance(&from.file()) > 0 => "O-O".to_string(),
+            MoveKind::Castle => "O-O-O".to_string(),
+            _ => {
+                let piece_type = position
+                    .get_piece_at(&from)
+                    .expect("no piece on the move's from square")
+                    .piece_type();
+                let is_capture = matches!(self.kind(), MoveKind::Capture | MoveKind::EnPassant)
+                    || position.get_piece_at(&to).is_some();
+
+                let mut san = String::new();
+                if piece_type == PieceType::Pawn {
+                    if is_capture {
+                        san.push_str(&from.file().to_string());
+                    }
+                } else {
+                    san.push_str(&piece_type.to_string().to_uppercase());
+                    san.push_str(&self.disambiguation(position, piece_type));
+                }
+
+                if is_capture {
+                    san.push('x');
+                }
+
+                san.push_str(&to.to_string());
+                if let MoveKind::Promotion(promotion) = self.kind() {
+                    san.push('=');
+                    san.push_str(&promotion.to_string().to_uppercase());
+                }
+
+                san
+            }
+        };
+
+        let mut after = position.clone();
+        after.make_move(*self, &mut Vec::new());
+        if after.is_checked(after.side_to_move()) {
+            san.push(if generate_legal_moves(&after).is_empty() {
+                '#'
+            } else {
+                '+'
+            });
+        }
+
+        san
+    }
+
+    /// Parses a move in Standard Algebraic Notation, matching it against the legal moves of
+    /// `position`.
+    ///
+    /// Check, mate and annotation suffixes are ignored, as is whether a capture is marked with
+    /// `x`. Castling may be written with letter O or digit zero.
+    pub fn from_san(san: &str, position: &Position) -> Result<Move, Error> {
+        let error = |reason| Error::InvalidSan {
+            san: san.to_string(),
+            reason,
+        };
+        let body = san.trim_end_matches(SAN_SUFFIXES);
+        let legal_moves = generate_legal_moves(position);
+
+        let castle_file = match body {
+            "O-O" | "0-0" => Some(File::G),
+            "O-O-O" | "0-0-0" => Some(File::C),
+            _ => None,
+        };
+        if let Some(castle_file) = castle_file {
+            return legal_moves
+                .into_iter()
+                .find(|mv| {
+                    mv.kind() == MoveKind::Castle && mv.castling_squares().0.file() == castle_file
+                })
+                .ok_or_else(|| error(SanError::Illegal));
+        }
+
+        let pattern = SanPattern::parse(body).ok_or_else(|| error(SanError::Syntax))?;
+        let candidates: Vec<Move> = legal_moves
+            .into_iter()
+            .filter(|mv| pattern.matches(*mv, position))
+            .collect();
+
+        match candidates[..] {
+            [mv] => match (mv.kind(), pattern.promotion) {
+                (MoveKind::Promotion(_), None) => Err(error(SanError::MissingPromotion)),
+                _ => Ok(mv),
+            },
+            [] => Err(error(SanError::Illegal)),
+            _ if pattern.promotion.is_none()
+                && candidates
+                    .iter()
+                    .all(|mv| matches!(mv.kind(), MoveKind::Promotion(_))) =>
+            {
+                Err(error(SanError::MissingPromotion))
+            }
+            _ => Err(error(SanError::Ambiguous(
+                candidates.iter().map(|mv| mv.to_uci_string()).collect(),
+            ))),
+        }
+    }
+
+    /// Returns the shortest prefix of the from square that tells this move apart from other legal
+    /// moves of the same piece type to the same square.
+    fn disambiguation(&self, position: &Position, piece_type: PieceType) -> String {
+        let from = self.from_sq();
+        let rivals: Vec<Square> = generate_legal_moves(position)
+            .into_iter()
+            .filter(|mv| mv.to_sq() == self.to_sq() && mv.from_sq() != from)
+            .map(|mv| mv.from_sq())
+            .filter(|square| {
+                position
+                    .get_piece_at(square)
+                    .is_some_and(|piece| piece.piece_type() == piece_type)
+            })
+            .collect();
+
+        if rivals.is_empty() {
+            String::new()
+        } else if rivals.iter().all(|square| square.file() != from.file()) {
+            from.file().to_string()
+        } else if rivals.iter().all(|square| square.rank() != from.rank()) {
+            from.rank().to_string()
+        } else {
+            from.to_string()
+        }
+    }
+}
+
+/// The constraints a non-castling SAN move places on the move it describes.
+struct SanPattern {
+    piece_type: PieceType,
+    from_file: Option<File>,
+    from_rank: Option<Rank>,
+    to: Square,
+    promotion: Option<PieceType>,
+}
+
+impl SanPattern {
+    fn parse(body: &str) -> Option<Self> {
+        if !body.is_ascii() {
+            return None;
+        }
+
+        // Promotions may be written with or without the '='
+        let (body, promotion) = match body.strip_suffix(['N', 'B', 'R', 'Q']) {
+            Some(rest) if rest.ends_with(|c: char| c == '=' || c.is_ascii_digit()) => {
+                let promotion = body[rest.len()..].parse().ok()?;
+                (rest.strip_suffix('=').unwrap_or(rest), Some(promotion))
+            }
+            _ => (body, None),
+        };
+
+        let split = body.len().checked_sub(2)?;
+        let to: Square = body[split..].parse().ok()?;
+        let mut prefix = &body[..split];
+
+        let piece_type = match prefix.chars().next() {
+            Some(c @ ('N' | 'B' | 'R' | 'Q' | 'K')) => {
+                prefix = &prefix[1..];
+                c.to_string().parse().ok()?
+            }
+            _ => PieceType::Pawn,
+        };
+
+        prefix = prefix.strip_suffix('x').unwrap_or(prefix);
+        let mut from_file = None;
+        let mut from_rank = None;
+        for c in prefix.chars() {
+            match c {
+                'a'..='h' if from_file.is_none() && from_rank.is_none() => {
+                    from_file = Some(c.to_string().parse().ok()?);
+                }
+                '1'..='8' if from_rank.is_none() => {
+                    from_rank = Some(c.to_string().parse().ok()?);
+                }
+                _ => return None,
+            }
+        }
+
+        if promotion.is_some() && piece_type != PieceType::Pawn {
+            return None;
+        }
+
+        Some(Self {
+            piece_type,
+            from_file,
+            from_rank,
+            to,
+            promotion,
+        })
+    }
+
+    fn matches(&self, mv: Move, position: &Position) -> bool {
+        let from = mv.from_sq();
+        let moved_type = position.get_piece_at(&from).map(|piece| piece.piece_type());
+
+        // A pawn move without a file is a push, so it cannot be a capture onto the target
+        let implied_file = match self.piece_type {
+            PieceType::Pawn => self.from_file.or(Some(self.to.file())),
+            _ => self.from_file,
+        };
+
+        mv.kind() != MoveKind::Castle
+            && mv.to_sq() == self.to
+            && moved_type == Some(self.piece_type)
+            && implied_file.is_none_or(|file| from.file() == file)
+            && self.from_rank.is_none_or(|rank| from.rank() == rank)
+            && match (mv.kind(), self.promotion) {
+                (MoveKind::Promotion(promotion), Some(expected)) => promotion == expected,
+                (_, Some(_)) => false,
+                _ => true,
+            }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::chess::STARTING_FEN;
+
+    use super::*;
+
+    fn position(fen: &str) -> Position {
+        Position::from_str(fen).unwrap()
+    }
+
+    fn san_error(san: &str, fen: &str) -> SanError {
+        match Move::from_san(san, &position(fen)) {
+            Err(Error::InvalidSan { reason, .. }) => reason,
+            result => panic!("expected SAN error for '{san}', got {result:?}"),
+        }
+    }
+
+    /// Checks that every legal move of `fen` formats to the expected SAN and parses back.
+    fn assert_round_trip(fen: &str) {
+        let position = position(fen);
+        for mv in generate_legal_moves(&position) {
+            let san = mv.to_san(&position);
+            assert_eq!(
+                Ok(mv),
+                Move::from_san(&san, &position).map_err(|_| ()),
+                "{san}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_to_san() {
+        let start = position(STARTING_FEN);
+        let kiwipete =
+            position("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+
+        for (position, from, to, kind, expected) in [
+            (&start, Square::E2, Square::E4, MoveKind::Quiet, "e4"),
+            (&start, Square::G1, Square::F3, MoveKind::Quiet, "Nf3"),
+            (&kiwipete, Square::E1, Square::H1, MoveKind::Castle, "O-O"),
+            (&kiwipete, Square::E1, Square::A1, MoveKind::Castle, "O-O-O"),
+            (&kiwipete, Square::E5, Square::F7, MoveKind::Capture, "Nxf7"),
+            (&kiwipete, Square::D5, Square::E6, MoveKind::Capture, "dxe6"),
+            (&kiwipete, Square::F3, Square::H3, MoveKind::Capture, "Qxh3"),
+            (&kiwipete, Square::E2, Square::A6, MoveKind::Capture, "Bxa6"),
+        ] {
+            assert_eq!(expected, Move::new(from, to, kind).to_san(position));
+        }
+    }
+
+    #[test]
+    fn test_to_san_disambiguation() {
+        // Knights on b1 and f1 both reach d2, rooks on a1 and a5 both reach a3
+        let p = position("4k3/8/8/R7/8/8/8/RN2KN2 w - - 0 1");
+        assert_eq!(
+            "Nbd2",
+            Move::new(Square::B1, Square::D2, MoveKind::Quiet).to_san(&p)
+        );
+        assert_eq!(
+            "R1a3",
+            Move::new(Square::A1, Square::A3, MoveKind::Quiet).to_san(&p)
+        );
+
+        // Queens on d1, h1 and h5 all reach e2 and f3, so some need both file and rank
+        let p = position("1k6/8/8/7Q/8/8/8/3QK2Q w - - 0 1");
+        assert_eq!(
+            "Qh1f3",
+            Move::new(Square::H1, Square::F3, MoveKind::Quiet).to_san(&p)
+        );
+        assert_eq!(
+            "Qdf3",
+            Move::new(Square::D1, Square::F3, MoveKind::Quiet).to_san(&p)
+        );
+        assert_eq!(
+            "Q5f3",
+            Move::new(Square::H5, Square::F3, MoveKind::Quiet).to_san(&p)
+        );
+    }
+
+    #[test]
+    fn test_to_san_special_moves() {
+        let p = position("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1");
+        assert_eq!(
+            "exd6",
+            Move::new(Square::E5, Square::D6, MoveKind::EnPassant).to_san(&p)
+        );
+
+        let p = position("8/4P3/8/8/8/8/8/k3K3 w - - 0 1");
+        assert_eq!(
+            "e8=Q",
+            Move::new(
+                Square::E7,
+                Square::E8,
+                MoveKind::Promotion(PieceType::Queen)
+            )
+            .to_san(&p)
+        );
+
+        let p = position("3rk3/4P3/8/8/8/8/8/4K3 w - - 0 1");
+        assert_eq!(
+            "exd8=N",
+            Move::new(
+                Square::E7,
+                Square::D8,
+                MoveKind::Promotion(PieceType::Knight)
+            )
+            .to_san(&p)
+        );
+        assert_eq!(
+            "exd8=Q+",
+            Move::new(
+                Square::E7,
+                Square::D8,
+                MoveKind::Promotion(PieceType::Queen)
+            )
+            .to_san(&p)
+        );
+
+        let p = position("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+        assert_eq!(
+            "Ra8#",
+            Move::new(Square::A1, Square::A8, MoveKind::Quiet).to_san(&p)
+        );
+    }
+
+    #[test]
+    fn test_from_san() {
+        let p = position("r2qk2r/ppp2ppp/2n2n2/3pP3/8/2N2N2/PPP2PPP/R3K2R w KQkq d6 0 1");
+
+        for (san, from, to, kind) in [
+            ("exd6", Square::E5, Square::D6, MoveKind::EnPassant),
+            ("e6", Square::E5, Square::E6, MoveKind::Quiet),
+            ("exf6", Square::E5, Square::F6, MoveKind::Capture),
+            ("Nxd5", Square::C3, Square::D5, MoveKind::Capture),
+            ("Nd4", Square::F3, Square::D4, MoveKind::Quiet),
+            ("Ng5!?", Square::F3, Square::G5, MoveKind::Quiet),
+            ("O-O", Square::E1, Square::H1, MoveKind::Castle),
+            ("0-0-0", Square::E1, Square::A1, MoveKind::Castle),
+        ] {
+            assert_eq!(
+                Move::new(from, to, kind),
+                Move::from_san(san, &p).unwrap(),
+                "{san}"
+            );
+        }
+
+        let p = position("r3k3/1P6/8/8/8/8/8/4K3 w - - 0 1");
+        assert_eq!(
+            Move::new(
+                Square::B7,
+                Square::A8,
+                MoveKind::Promotion(PieceType::Knight)
+            ),
+            Move::from_san("bxa8=N+", &p).unwrap()
+        );
+        assert_eq!(
+            Move::new(
+                Square::B7,
+                Square::B8,
+                MoveKind::Promotion(PieceType::Queen)
+            ),
+            Move::from_san("b8Q+", &p).unwrap()
+        );
+
+        let p = position("4k3/8/8/8/8/8/8/RN2KN2 w - - 0 1");
+        assert_eq!(
+            Move::new(Square::B1, Square::D2, MoveKind::Quiet),
+            Move::from_san("Nbd2", &p).unwrap()
+        );
+        assert_eq!(
+            Move::new(Square::B1, Square::D2, MoveKind::Quiet),
+            Move::from_san("Nb1d2", &p).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_from_san_errors() {
+        assert_eq!(SanError::Syntax, san_error("", STARTING_FEN));
+        assert_eq!(SanError::Syntax, san_error("Pe4", STARTING_FEN));
+        assert_eq!(SanError::Syntax, san_error("Nf3=Q", STARTING_FEN));
+        assert_eq!(SanError::Syntax, san_error("e9", STARTING_FEN));
+        assert_eq!(SanError::Illegal, san_error("e5", STARTING_FEN));
+        assert_eq!(SanError::Illegal, san_error("O-O", STARTING_FEN));
+        assert_eq!(SanError::Illegal, san_error("Ke2", STARTING_FEN));
+        assert_eq!(
+            SanError::Ambiguous(vec!["b1d2".to_string(), "f1d2".to_string()]),
+            san_error("Nd2", "4k3/8/8/8/8/8/8/RN2KN2 w - - 0 1")
+        );
+        assert_eq!(
+            SanError::MissingPromotion,
+            san_error("e8", "8/4P3/8/8/8/8/8/k3K3 w - - 0 1")
+        );
+    }
+
+    #[test]
+    fn test_san_round_trip() {
+        for fen in [
+            STARTING_FEN,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            "1k6/8/8/7Q/8/8/8/3QK2Q w - - 0 1",
+        ] {
+            assert_round_trip(fen);
+        }
+    }
+}
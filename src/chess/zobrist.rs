@@ -0,0 +1,127 @@
+use lazy_static::{initialize, lazy_static};
+
+use crate::chess::{
+    CastlingRights, Color, NUM_BITBOARDS, NUM_COLORS, NUM_FILES, NUM_PIECES, NUM_SQUARES, Piece,
+    Square,
+};
+
+/// Number of distinct [`CastlingRights`] values per color.
+const NUM_CASTLING_RIGHTS: usize = 4;
+
+/// Seed for the key generator, fixed so hashes are reproducible between runs.
+const SEED: u64 = 0x4174_6865_6e61_2121;
+
+lazy_static! {
+    static ref KEYS: ZobristKeys = ZobristKeys::new();
+}
+
+/// Random keys used to build [Zobrist hashes](https://www.chessprogramming.org/Zobrist_Hashing).
+///
+/// A position's hash is the XOR of the keys for every piece on its square, the side to move,
+/// each color's castling rights and the en passant file. Since XOR is its own inverse, making a
+/// move only needs to toggle the keys of whatever changed.
+struct ZobristKeys {
+    pieces: [[u64; NUM_SQUARES]; NUM_BITBOARDS],
+    side_to_move: u64,
+    castling_rights: [[u64; NUM_CASTLING_RIGHTS]; NUM_COLORS],
+    en_passant: [u64; NUM_FILES],
+}
+
+impl ZobristKeys {
+    fn new() -> Self {
+        let mut rng = XorShift(SEED);
+        let mut keys = Self {
+            pieces: [[0; NUM_SQUARES]; NUM_BITBOARDS],
+            side_to_move: rng.next(),
+            castling_rights: [[0; NUM_CASTLING_RIGHTS]; NUM_COLORS],
+            en_passant: [0; NUM_FILES],
+        };
+
+        keys.pieces
+            .iter_mut()
+            .flatten()
+            .chain(keys.castling_rights.iter_mut().flatten())
+            .chain(keys.en_passant.iter_mut())
+            .for_each(|key| *key = rng.next());
+
+        // Having no rights contributes nothing, so positions without castling hash the same
+        // regardless of how the rights were lost
+        for rights in keys.castling_rights.iter_mut() {
+            rights[CastlingRights::None as usize] = 0;
+        }
+
+        keys
+    }
+}
+
+/// Generates the hash keys ahead of their first use.
+pub(crate) fn init_zobrist() {
+    initialize(&KEYS);
+}
+
+/// Xorshift64* pseudo-random number generator.
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+}
+
+pub(crate) fn piece_key(piece: Piece, square: Square) -> u64 {
+    let index = piece.color() as usize * NUM_PIECES + piece.piece_type() as usize;
+    KEYS.pieces[index][square.lsf_index()]
+}
+
+pub(crate) fn side_to_move_key() -> u64 {
+    KEYS.side_to_move
+}
+
+pub(crate) fn castling_key(color: Color, rights: CastlingRights) -> u64 {
+    KEYS.castling_rights[color as usize][rights as usize]
+}
+
+pub(crate) fn en_passant_key(square: Square) -> u64 {
+    KEYS.en_passant[square.file() as usize]
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chess::PieceType;
+
+    use super::*;
+
+    #[test]
+    fn test_keys_are_distinct() {
+        let mut keys: Vec<u64> = KEYS
+            .pieces
+            .iter()
+            .flatten()
+            .chain(KEYS.castling_rights.iter().flatten())
+            .chain(KEYS.en_passant.iter())
+            .chain(std::iter::once(&KEYS.side_to_move))
+            .copied()
+            .filter(|&key| key != 0)
+            .collect();
+        let total = keys.len();
+        keys.sort_unstable();
+        keys.dedup();
+
+        assert_eq!(total, keys.len());
+        assert_eq!(
+            NUM_BITBOARDS * NUM_SQUARES + NUM_COLORS * (NUM_CASTLING_RIGHTS - 1) + NUM_FILES + 1,
+            total
+        );
+    }
+
+    #[test]
+    fn test_piece_key_depends_on_color() {
+        let white = piece_key(Piece::new(Color::White, PieceType::Pawn), Square::E4);
+        let black = piece_key(Piece::new(Color::Black, PieceType::Pawn), Square::E4);
+
+        assert_ne!(white, black);
+    }
+}
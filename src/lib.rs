@@ -1 +1,4 @@
 pub mod chess;
+pub mod eval;
+pub mod search;
+pub mod uci;
@@ -1,3 +1,152 @@
-fn main() {
-    println!("Hello World!");
+use std::{
+    env, fs,
+    io::{self, BufRead},
+    process::ExitCode,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use athena::{
+    chess::{
+        Position, STARTING_FEN,
+        movegen::init_movegen,
+        perft::{PerftEntry, divide, perft},
+    },
+    uci::Uci,
+};
+
+const USAGE: &str = "\
+usage: athena                                 run the UCI protocol on stdin
+       athena perft <depth> [<fen>]           print perft node counts per root move
+       athena perft --suite <file> [<depth>]  check an EPD perft suite, up to an optional depth";
+
+fn main() -> ExitCode {
+    init_movegen();
+
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        [] => Uci::new().run(io::stdin().lock()).map(|_| true),
+        ["perft", "--suite", file] => run_suite(file, None),
+        ["perft", "--suite", file, depth] => match depth.parse() {
+            Ok(depth) => run_suite(file, Some(depth)),
+            Err(_) => return usage(),
+        },
+        ["perft", depth, ref fen @ ..] => match depth.parse() {
+            Ok(depth) => run_divide(&fen.join(" "), depth),
+            Err(_) => return usage(),
+        },
+        _ => return usage(),
+    };
+
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn usage() -> ExitCode {
+    eprintln!("{USAGE}");
+    ExitCode::FAILURE
+}
+
+/// Prints the perft count of every root move in `e2e4: N` form, followed by the total.
+fn run_divide(fen: &str, depth: u32) -> io::Result<bool> {
+    let fen = if fen.is_empty() { STARTING_FEN } else { fen };
+    let position = match Position::from_str(fen) {
+        Ok(position) => position,
+        Err(err) => {
+            eprintln!("error: {err}");
+            return Ok(false);
+        }
+    };
+
+    let start = Instant::now();
+    let divide = divide(&position, depth);
+    let elapsed = start.elapsed();
+    // At depth 0 the root itself is the only node, and there are no moves to divide it by
+    let nodes = if depth == 0 {
+        perft(&position, depth)
+    } else {
+        divide.iter().map(|&(_, nodes)| nodes).sum()
+    };
+    for (mv, nodes) in divide {
+        println!("{}: {nodes}", mv.to_uci(position.is_chess960()));
+    }
+
+    println!("\nNodes searched: {nodes}");
+    eprintln!(
+        "Time: {}ms ({} nps)",
+        elapsed.as_millis(),
+        nodes_per_second(nodes, elapsed)
+    );
+
+    Ok(true)
+}
+
+/// Runs every entry of an EPD perft suite, reporting mismatches and overall speed.
+///
+/// Returns whether every line parsed and every checked count matched.
+fn run_suite(path: &str, max_depth: Option<u32>) -> io::Result<bool> {
+    let file = fs::File::open(path)?;
+    let mut checked = 0;
+    let mut mismatches = 0;
+    let mut invalid_lines = 0;
+    let mut total_nodes = 0;
+    let mut total_time = Duration::ZERO;
+    for (number, line) in io::BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let entry = match PerftEntry::from_str(&line) {
+            Ok(entry) => entry,
+            Err(err) => {
+                println!("line {}: {err}", number + 1);
+                invalid_lines += 1;
+                continue;
+            }
+        };
+
+        for &(depth, expected) in &entry.expected {
+            if max_depth.is_some_and(|max_depth| depth > max_depth) {
+                continue;
+            }
+
+            let start = Instant::now();
+            let nodes = perft(&entry.position, depth);
+            total_time += start.elapsed();
+            total_nodes += nodes;
+            checked += 1;
+
+            if nodes != expected {
+                mismatches += 1;
+                println!(
+                    "line {}: {} depth {depth}: expected {expected}, found {nodes}",
+                    number + 1,
+                    entry.fen
+                );
+            }
+        }
+    }
+
+    println!(
+        "\n{} of {checked} counts matched, {invalid_lines} invalid lines",
+        checked - mismatches
+    );
+    println!(
+        "Nodes: {total_nodes}, time: {}ms, {} nps",
+        total_time.as_millis(),
+        nodes_per_second(total_nodes, total_time)
+    );
+
+    Ok(mismatches == 0 && invalid_lines == 0)
+}
+
+fn nodes_per_second(nodes: u64, elapsed: Duration) -> u64 {
+    (nodes as f64 / elapsed.as_secs_f64().max(f64::EPSILON)) as u64
 }
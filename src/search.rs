@@ -0,0 +1,15 @@
+mod history;
+mod limits;
+mod movepick;
+mod options;
+mod pv;
+mod searcher;
+mod signals;
+mod time;
+mod tt;
+
+pub use limits::*;
+pub use options::*;
+pub use searcher::*;
+pub use signals::*;
+pub use tt::*;
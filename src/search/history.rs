@@ -0,0 +1,213 @@
+use crate::{
+    chess::{NUM_COLORS, NUM_PIECES, NUM_SQUARES, Piece, Position, Square, movegen::Move},
+    search::MAX_PLY,
+};
+
+/// Scores are kept within this bound in either direction.
+const MAX_HISTORY: i32 = 16_384;
+
+/// Number of distinct pieces, each color's piece types counted separately.
+const NUM_PIECE_KINDS: usize = NUM_COLORS * NUM_PIECES;
+
+/// A move identified by the piece that made it and its destination, which is what the
+/// continuation history and countermove tables are indexed by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct PieceTo {
+    piece: Piece,
+    to: Square,
+}
+
+impl PieceTo {
+    /// Identifies a move of the side to move in `position`, before it is made.
+    pub fn new(position: &Position, mv: Move) -> Self {
+        let piece = position
+            .get_piece_at(&mv.from_sq())
+            .expect("no piece at from square");
+        Self {
+            piece,
+            to: mv.to_sq(),
+        }
+    }
+
+    fn index(&self) -> usize {
+        (self.piece.color() as usize * NUM_PIECES + self.piece.piece_type() as usize) * NUM_SQUARES
+            + self.to as usize
+    }
+}
+
+/// Returns the history bonus for a move causing a cutoff at `depth`, deeper searches giving
+/// more reliable results.
+fn history_bonus(depth: u32) -> i32 {
+    (32 * (depth * depth) as i32).min(1_200)
+}
+
+/// Moves `entry` towards `bonus`, by less the closer it already is to the bound, so frequently
+/// updated entries saturate instead of growing without limit.
+fn update_entry(entry: &mut i16, bonus: i32) {
+    let bonus = bonus.clamp(-MAX_HISTORY, MAX_HISTORY);
+    let value = *entry as i32;
+    *entry = (value + bonus - value * bonus.abs() / MAX_HISTORY) as i16;
+}
+
+/// Statistics about which quiet moves caused cutoffs, used to order quiet moves.
+///
+/// `previous` arguments are the moves played one and two plies before the current one, if any.
+pub(crate) struct MoveHistory {
+    /// Two moves per ply that recently caused a cutoff there.
+    killers: [[Move; 2]; MAX_PLY],
+    /// The move that refuted each opponent move, by the opponent's piece and destination.
+    counter_moves: Vec<Move>,
+    /// Scores by side to move, from square and to square, regardless of the position.
+    butterfly: Vec<[[i16; NUM_SQUARES]; NUM_SQUARES]>,
+    /// Scores of a move by its piece and destination, given a move played before it.
+    continuation: Vec<[i16; NUM_PIECE_KINDS * NUM_SQUARES]>,
+}
+
+impl MoveHistory {
+    pub fn new() -> Self {
+        Self {
+            killers: [[Move::NULL; 2]; MAX_PLY],
+            counter_moves: vec![Move::NULL; NUM_PIECE_KINDS * NUM_SQUARES],
+            butterfly: vec![[[0; NUM_SQUARES]; NUM_SQUARES]; NUM_COLORS],
+            continuation: vec![[0; NUM_PIECE_KINDS * NUM_SQUARES]; NUM_PIECE_KINDS * NUM_SQUARES],
+        }
+    }
+
+    /// Halves every history score, so that statistics gathered at shallow depths give way to
+    /// those of the deeper iterations that follow.
+    pub fn age(&mut self) {
+        for table in self.butterfly.iter_mut() {
+            for entry in table.iter_mut().flatten() {
+                *entry /= 2;
+            }
+        }
+
+        for entry in self.continuation.iter_mut().flatten() {
+            *entry /= 2;
+        }
+    }
+
+    pub fn killers(&self, ply: usize) -> [Move; 2] {
+        self.killers[ply]
+    }
+
+    /// Returns the move that last refuted `previous`, or [`Move::NULL`] if none has.
+    pub fn counter_move(&self, previous: Option<PieceTo>) -> Move {
+        previous.map_or(Move::NULL, |previous| self.counter_moves[previous.index()])
+    }
+
+    /// Scores a quiet move of the side to move by how often it caused cutoffs before.
+    pub fn quiet_score(
+        &self,
+        position: &Position,
+        mv: Move,
+        previous: [Option<PieceTo>; 2],
+    ) -> i32 {
+        let moved = PieceTo::new(position, mv);
+        let color = position.side_to_move() as usize;
+        let mut score = self.butterfly[color][mv.from_sq() as usize][mv.to_sq() as usize] as i32;
+        for previous in previous.into_iter().flatten() {
+            score += self.continuation[previous.index()][moved.index()] as i32;
+        }
+
+        score
+    }
+
+    /// Records that the quiet move `best` caused a cutoff at `ply`, after the quiet moves in
+    /// `tried` failed to.
+    pub fn update_quiets(
+        &mut self,
+        position: &Position,
+        ply: usize,
+        depth: u32,
+        best: Move,
+        tried: &[Move],
+        previous: [Option<PieceTo>; 2],
+    ) {
+        let killers = &mut self.killers[ply];
+        if killers[0] != best {
+            killers[1] = killers[0];
+            killers[0] = best;
+        }
+
+        if let Some(previous) = previous[0] {
+            self.counter_moves[previous.index()] = best;
+        }
+
+        let bonus = history_bonus(depth);
+        self.update_quiet(position, best, bonus, previous);
+        for &mv in tried {
+            self.update_quiet(position, mv, -bonus, previous);
+        }
+    }
+
+    fn update_quiet(
+        &mut self,
+        position: &Position,
+        mv: Move,
+        bonus: i32,
+        previous: [Option<PieceTo>; 2],
+    ) {
+        let moved = PieceTo::new(position, mv);
+        let color = position.side_to_move() as usize;
+        update_entry(
+            &mut self.butterfly[color][mv.from_sq() as usize][mv.to_sq() as usize],
+            bonus,
+        );
+        for previous in previous.into_iter().flatten() {
+            update_entry(
+                &mut self.continuation[previous.index()][moved.index()],
+                bonus,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chess::{Color, PieceType, movegen::MoveKind};
+
+    use super::*;
+
+    #[test]
+    fn test_update_quiets() {
+        let position = Position::default();
+        let previous = PieceTo {
+            piece: Piece::new(Color::Black, PieceType::Pawn),
+            to: Square::E5,
+        };
+        let previous = [Some(previous), None];
+        let best = Move::new(Square::G1, Square::F3, MoveKind::Quiet);
+        let tried = Move::new(Square::B1, Square::C3, MoveKind::Quiet);
+
+        let mut history = MoveHistory::new();
+        history.update_quiets(&position, 3, 4, best, &[tried], previous);
+
+        assert_eq!([best, Move::NULL], history.killers(3));
+        assert_eq!(best, history.counter_move(previous[0]));
+        assert!(history.quiet_score(&position, best, previous) > 0);
+        assert!(history.quiet_score(&position, tried, previous) < 0);
+        assert!(
+            history.quiet_score(&position, best, [None; 2])
+                < history.quiet_score(&position, best, previous)
+        );
+    }
+
+    #[test]
+    fn test_scores_saturate_and_age() {
+        let mut entry = 0;
+        for _ in 0..1_000 {
+            update_entry(&mut entry, 1_200);
+        }
+        assert!((entry as i32) <= MAX_HISTORY);
+        assert!((entry as i32) > MAX_HISTORY * 9 / 10);
+
+        let position = Position::default();
+        let mv = Move::new(Square::G1, Square::F3, MoveKind::Quiet);
+        let mut history = MoveHistory::new();
+        history.update_quiets(&position, 0, 6, mv, &[], [None; 2]);
+        let score = history.quiet_score(&position, mv, [None; 2]);
+        history.age();
+        assert_eq!(score / 2, history.quiet_score(&position, mv, [None; 2]));
+    }
+}
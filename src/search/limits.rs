@@ -0,0 +1,63 @@
+use std::time::Duration;
+
+/// The side to move's clock in a timed game.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Clock {
+    /// Time left on the clock.
+    pub time: Duration,
+    /// Time added to the clock after each move.
+    pub increment: Duration,
+    /// Moves to play before the next time control, or `None` if the remaining time must last
+    /// the rest of the game.
+    pub moves_to_go: Option<u32>,
+}
+
+/// Constraints on how long a search may run.
+///
+/// A search stops as soon as any of the configured limits is reached. When no limits are set,
+/// the search runs until it is stopped externally or the maximum search depth is reached.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchLimits {
+    /// Maximum number of plies to search.
+    pub depth: Option<u32>,
+    /// Maximum number of nodes to visit.
+    pub nodes: Option<u64>,
+    /// Maximum wall-clock time to spend searching.
+    pub move_time: Option<Duration>,
+    /// Clock from which to budget the time to spend, when `move_time` is not set.
+    pub clock: Option<Clock>,
+    /// Time set aside from every time limit for the delay in communicating the move.
+    pub move_overhead: Duration,
+    /// Stop once a mate in this many moves (or fewer) has been found.
+    pub mate: Option<u32>,
+}
+
+impl SearchLimits {
+    pub fn depth(depth: u32) -> Self {
+        Self {
+            depth: Some(depth),
+            ..Default::default()
+        }
+    }
+
+    pub fn nodes(nodes: u64) -> Self {
+        Self {
+            nodes: Some(nodes),
+            ..Default::default()
+        }
+    }
+
+    pub fn move_time(move_time: Duration) -> Self {
+        Self {
+            move_time: Some(move_time),
+            ..Default::default()
+        }
+    }
+
+    pub fn clock(clock: Clock) -> Self {
+        Self {
+            clock: Some(clock),
+            ..Default::default()
+        }
+    }
+}
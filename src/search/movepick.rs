@@ -0,0 +1,410 @@
+use crate::{
+    chess::{
+        PieceType, Position,
+        movegen::{GenType, MAX_MOVES, Move, MoveKind, MoveList, generate, is_pseudo_legal},
+    },
+    search::history::{MoveHistory, PieceTo},
+};
+
+/// Bonus lifting captures above every history score when ordering check evasions.
+const EVASION_CAPTURE_BONUS: i32 = 1 << 20;
+
+/// Returns the value of the piece a move captures, or zero for a quiet move.
+pub(crate) fn captured_value(position: &Position, mv: Move) -> i32 {
+    match mv.kind() {
+        MoveKind::EnPassant => PieceType::Pawn.value(),
+        _ => position
+            .get_piece_at(&mv.to_sq())
+            .map_or(0, |piece| piece.piece_type().value()),
+    }
+}
+
+/// Scores a capture by most valuable victim, then least valuable attacker, counting the value a
+/// promotion adds as part of the victim.
+fn mvv_lva(position: &Position, mv: Move) -> i32 {
+    let attacker = position
+        .get_piece_at(&mv.from_sq())
+        .map_or(0, |piece| piece.piece_type() as i32);
+    let promotion = match mv.kind() {
+        MoveKind::Promotion(piece_type) => piece_type.value(),
+        _ => 0,
+    };
+
+    (captured_value(position, mv) + promotion) * 16 - attacker
+}
+
+fn is_quiet(mv: Move) -> bool {
+    matches!(mv.kind(), MoveKind::Quiet | MoveKind::Castle)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stage {
+    TtMove,
+    GenerateCaptures,
+    GoodCaptures,
+    Killers,
+    CounterMove,
+    GenerateQuiets,
+    Quiets,
+    BadCaptures,
+    GenerateEvasions,
+    Evasions,
+    Done,
+}
+
+/// Hands out the pseudo-legal moves of a position one at a time, best first, generating them in
+/// stages so that a cutoff on an early move saves generating and sorting the rest.
+///
+/// The main search order is: the transposition table move, captures that do not lose material
+/// by [SEE](Position::see_ge) in MVV-LVA order, the killer moves, the countermove, the remaining
+/// quiet moves by history score, and finally the losing captures. In check, every evasion is
+/// instead generated at once, captures first. Moves handed out by an earlier stage are never
+/// repeated by a later one.
+pub(crate) struct MovePicker {
+    stage: Stage,
+    tt_move: Move,
+    killers: [Move; 2],
+    counter_move: Move,
+    previous: [Option<PieceTo>; 2],
+    /// Whether only the good captures are wanted, as in quiescence search.
+    captures_only: bool,
+    /// Whether the remaining quiet moves were pruned by the search.
+    skip_quiets: bool,
+    moves: MoveList,
+    scores: [i32; MAX_MOVES],
+    index: usize,
+    bad_captures: MoveList,
+}
+
+impl MovePicker {
+    /// Creates a picker for the main search. `previous` holds the moves played one and two plies
+    /// before this position, if any.
+    pub fn new(
+        position: &Position,
+        tt_move: Option<Move>,
+        history: &MoveHistory,
+        ply: usize,
+        previous: [Option<PieceTo>; 2],
+    ) -> Self {
+        let tt_move = tt_move
+            .filter(|&mv| is_pseudo_legal(position, mv))
+            .unwrap_or(Move::NULL);
+        let stage = if !tt_move.is_null() {
+            Stage::TtMove
+        } else if position.is_checked(position.side_to_move()) {
+            Stage::GenerateEvasions
+        } else {
+            Stage::GenerateCaptures
+        };
+
+        Self {
+            stage,
+            tt_move,
+            killers: history.killers(ply),
+            counter_move: history.counter_move(previous[0]),
+            previous,
+            captures_only: false,
+            skip_quiets: false,
+            moves: MoveList::new(),
+            scores: [0; MAX_MOVES],
+            index: 0,
+            bad_captures: MoveList::new(),
+        }
+    }
+
+    /// Creates a picker for quiescence search, which hands out only the good captures, or every
+    /// evasion when `in_check`.
+    pub fn quiescence(in_check: bool) -> Self {
+        Self {
+            stage: if in_check {
+                Stage::GenerateEvasions
+            } else {
+                Stage::GenerateCaptures
+            },
+            tt_move: Move::NULL,
+            killers: [Move::NULL; 2],
+            counter_move: Move::NULL,
+            previous: [None; 2],
+            captures_only: true,
+            skip_quiets: false,
+            moves: MoveList::new(),
+            scores: [0; MAX_MOVES],
+            index: 0,
+            bad_captures: MoveList::new(),
+        }
+    }
+
+    /// Returns the next move to search, or `None` once every move has been handed out.
+    pub fn next(&mut self, position: &Position, history: &MoveHistory) -> Option<Move> {
+        loop {
+            match self.stage {
+                Stage::Killers | Stage::CounterMove | Stage::GenerateQuiets | Stage::Quiets
+                    if self.skip_quiets =>
+                {
+                    self.index = 0;
+                    self.stage = Stage::BadCaptures;
+                }
+                Stage::TtMove => {
+                    self.stage = if position.is_checked(position.side_to_move()) {
+                        Stage::GenerateEvasions
+                    } else {
+                        Stage::GenerateCaptures
+                    };
+                    return Some(self.tt_move);
+                }
+                Stage::GenerateCaptures => {
+                    self.generate(position, GenType::Captures);
+                    for i in 0..self.moves.len() {
+                        self.scores[i] = self.moves.get(i).map_or(0, |mv| mvv_lva(position, mv));
+                    }
+                    self.stage = Stage::GoodCaptures;
+                }
+                Stage::GoodCaptures => {
+                    while let Some(mv) = self.select() {
+                        if mv == self.tt_move {
+                            continue;
+                        }
+
+                        if !position.see_ge(mv, 0) {
+                            self.bad_captures.push(mv);
+                            continue;
+                        }
+
+                        return Some(mv);
+                    }
+
+                    self.index = 0;
+                    self.stage = if self.captures_only {
+                        Stage::Done
+                    } else {
+                        Stage::Killers
+                    };
+                }
+                Stage::Killers => {
+                    while let Some(&killer) = self.killers.get(self.index) {
+                        self.index += 1;
+                        if killer != self.tt_move && self.is_valid_quiet(position, killer) {
+                            return Some(killer);
+                        }
+                    }
+
+                    self.stage = Stage::CounterMove;
+                }
+                Stage::CounterMove => {
+                    self.stage = Stage::GenerateQuiets;
+                    let counter_move = self.counter_move;
+                    if counter_move != self.tt_move
+                        && !self.killers.contains(&counter_move)
+                        && self.is_valid_quiet(position, counter_move)
+                    {
+                        return Some(counter_move);
+                    }
+                }
+                Stage::GenerateQuiets => {
+                    self.generate(position, GenType::Quiets);
+                    for i in 0..self.moves.len() {
+                        self.scores[i] = self
+                            .moves
+                            .get(i)
+                            .map_or(0, |mv| history.quiet_score(position, mv, self.previous));
+                    }
+                    self.stage = Stage::Quiets;
+                }
+                Stage::Quiets => {
+                    while let Some(mv) = self.select() {
+                        if mv != self.tt_move
+                            && !self.killers.contains(&mv)
+                            && mv != self.counter_move
+                        {
+                            return Some(mv);
+                        }
+                    }
+
+                    self.index = 0;
+                    self.stage = Stage::BadCaptures;
+                }
+                Stage::BadCaptures => {
+                    let mv = self.bad_captures.get(self.index);
+                    self.index += 1;
+                    if mv.is_none() {
+                        self.stage = Stage::Done;
+                    }
+                    return mv;
+                }
+                Stage::GenerateEvasions => {
+                    self.generate(position, GenType::Evasions);
+                    for i in 0..self.moves.len() {
+                        self.scores[i] = self.moves.get(i).map_or(0, |mv| {
+                            if is_quiet(mv) {
+                                history.quiet_score(position, mv, self.previous)
+                            } else {
+                                EVASION_CAPTURE_BONUS + mvv_lva(position, mv)
+                            }
+                        });
+                    }
+                    self.stage = Stage::Evasions;
+                }
+                Stage::Evasions => {
+                    while let Some(mv) = self.select() {
+                        if mv != self.tt_move {
+                            return Some(mv);
+                        }
+                    }
+
+                    self.stage = Stage::Done;
+                }
+                Stage::Done => return None,
+            }
+        }
+    }
+
+    /// Stops handing out quiet moves, for when the search has decided none of the remaining
+    /// ones are worth searching. Losing captures are still handed out after the quiet stages.
+    pub fn skip_quiets(&mut self) {
+        self.skip_quiets = true;
+    }
+
+    fn generate(&mut self, position: &Position, gen_type: GenType) {
+        self.moves = MoveList::new();
+        generate(position, gen_type, &mut self.moves);
+        self.index = 0;
+    }
+
+    /// Hands out the best remaining generated move.
+    fn select(&mut self) -> Option<Move> {
+        let mv = self.moves.select_best(self.index, &mut self.scores)?;
+        self.index += 1;
+        Some(mv)
+    }
+
+    /// Returns whether a killer or countermove, remembered from another position, is a quiet
+    /// move that can be played in this one.
+    fn is_valid_quiet(&self, position: &Position, mv: Move) -> bool {
+        !mv.is_null() && is_quiet(mv) && is_pseudo_legal(position, mv)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::chess::{Square, movegen::generate_moves};
+
+    use super::*;
+
+    const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+    fn picked(mut picker: MovePicker, position: &Position, history: &MoveHistory) -> Vec<Move> {
+        let mut moves = Vec::new();
+        while let Some(mv) = picker.next(position, history) {
+            moves.push(mv);
+        }
+
+        moves
+    }
+
+    #[test]
+    fn test_picks_every_move_once() {
+        let history = MoveHistory::new();
+        for fen in [
+            KIWIPETE,
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            // In check
+            "rnbqkbnr/ppp2ppp/8/1B1pp3/4P3/8/PPPP1PPP/RNBQK1NR b KQkq - 1 3",
+        ] {
+            let position: Position = fen.parse().unwrap();
+            let mut expected = MoveList::new();
+            if position.is_checked(position.side_to_move()) {
+                generate(&position, GenType::Evasions, &mut expected);
+            } else {
+                generate_moves(&position, &mut expected);
+            }
+            let expected: HashSet<_> = expected.into_iter().collect();
+
+            let tt_move = expected.iter().copied().min_by_key(|mv| mv.to_bits());
+            let picker = MovePicker::new(&position, tt_move, &history, 0, [None; 2]);
+            let moves = picked(picker, &position, &history);
+
+            assert_eq!(tt_move, moves.first().copied(), "{fen}");
+            assert_eq!(expected.len(), moves.len(), "{fen}");
+            assert_eq!(expected, moves.into_iter().collect(), "{fen}");
+        }
+    }
+
+    #[test]
+    fn test_stage_order() {
+        let position: Position = KIWIPETE.parse().unwrap();
+        let killer = Move::new(Square::A2, Square::A3, MoveKind::Quiet);
+        let counter_move = Move::new(Square::G2, Square::G3, MoveKind::Quiet);
+        let previous = [Some(PieceTo::new(&position, killer)), None];
+        let mut history = MoveHistory::new();
+        history.update_quiets(&position, 1, 1, counter_move, &[], previous);
+        history.update_quiets(&position, 0, 1, killer, &[], [None; 2]);
+
+        let tt_move = Move::new(Square::E2, Square::A6, MoveKind::Capture);
+        let picker = MovePicker::new(&position, Some(tt_move), &history, 0, previous);
+        let moves = picked(picker, &position, &history);
+
+        let good_captures = moves[1..].iter().take_while(|&&mv| !is_quiet(mv)).count();
+        assert_eq!(tt_move, moves[0]);
+        assert_eq!(
+            &[killer, counter_move],
+            &moves[1 + good_captures..3 + good_captures]
+        );
+        assert!(
+            moves[1..=good_captures]
+                .iter()
+                .all(|&mv| position.see_ge(mv, 0))
+        );
+
+        // Losing captures come last, such as the queen taking a defended pawn
+        let bad_capture = Move::new(Square::F3, Square::H3, MoveKind::Capture);
+        assert!(!position.see_ge(bad_capture, 0));
+        let bad_captures = moves.iter().rev().take_while(|&&mv| !is_quiet(mv));
+        assert!(bad_captures.copied().any(|mv| mv == bad_capture));
+    }
+
+    #[test]
+    fn test_ignores_unplayable_moves() {
+        let position: Position = KIWIPETE.parse().unwrap();
+        let history = MoveHistory::new();
+        // A black move, and a move onto an occupied square with the wrong kind
+        let tt_move = Move::new(Square::A8, Square::B8, MoveKind::Quiet);
+        let picker = MovePicker::new(&position, Some(tt_move), &history, 0, [None; 2]);
+        assert!(!picked(picker, &position, &history).contains(&tt_move));
+
+        let quiet_capture = Move::new(Square::E5, Square::F7, MoveKind::Quiet);
+        let picker = MovePicker::new(&position, Some(quiet_capture), &history, 0, [None; 2]);
+        assert!(!picked(picker, &position, &history).contains(&quiet_capture));
+    }
+
+    #[test]
+    fn test_skip_quiets() {
+        let position: Position = KIWIPETE.parse().unwrap();
+        let history = MoveHistory::new();
+        let mut picker = MovePicker::new(&position, None, &history, 0, [None; 2]);
+        let first = picker.next(&position, &history).unwrap();
+        picker.skip_quiets();
+        let moves = picked(picker, &position, &history);
+
+        assert!(!is_quiet(first));
+        assert!(moves.iter().all(|&mv| !is_quiet(mv)));
+        assert!(moves.iter().any(|&mv| !position.see_ge(mv, 0)));
+    }
+
+    #[test]
+    fn test_quiescence_picks_good_captures() {
+        let position: Position = KIWIPETE.parse().unwrap();
+        let history = MoveHistory::new();
+        let moves = picked(MovePicker::quiescence(false), &position, &history);
+
+        assert!(!moves.is_empty());
+        assert!(
+            moves
+                .iter()
+                .all(|&mv| !is_quiet(mv) && position.see_ge(mv, 0))
+        );
+        let scores: Vec<_> = moves.iter().map(|&mv| mvv_lva(&position, mv)).collect();
+        assert!(scores.is_sorted_by(|a, b| a >= b));
+    }
+}
@@ -0,0 +1,38 @@
+/// Switches for the selective search techniques, so each can be disabled and its strength
+/// measured on its own. All of them are enabled by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchOptions {
+    pub null_move_pruning: bool,
+    pub late_move_reductions: bool,
+    pub reverse_futility_pruning: bool,
+    pub futility_pruning: bool,
+    pub late_move_pruning: bool,
+    pub razoring: bool,
+}
+
+impl SearchOptions {
+    /// Returns every switch along with the name of the UCI option toggling it.
+    pub fn toggles(&mut self) -> [(&'static str, &mut bool); 6] {
+        [
+            ("NullMovePruning", &mut self.null_move_pruning),
+            ("LateMoveReductions", &mut self.late_move_reductions),
+            ("ReverseFutilityPruning", &mut self.reverse_futility_pruning),
+            ("FutilityPruning", &mut self.futility_pruning),
+            ("LateMovePruning", &mut self.late_move_pruning),
+            ("Razoring", &mut self.razoring),
+        ]
+    }
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            null_move_pruning: true,
+            late_move_reductions: true,
+            reverse_futility_pruning: true,
+            futility_pruning: true,
+            late_move_pruning: true,
+            razoring: true,
+        }
+    }
+}
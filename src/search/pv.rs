@@ -0,0 +1,78 @@
+use crate::{chess::movegen::Move, search::MAX_PLY};
+
+/// Triangular table of principal variations.
+///
+/// Row `ply` holds the best line found so far starting at that ply. When a move improves alpha,
+/// the row is rebuilt from the move followed by the row of the next ply, so the full principal
+/// variation ends up in row 0 once the root returns.
+pub(crate) struct PvTable {
+    moves: Vec<[Move; MAX_PLY]>,
+    lengths: [usize; MAX_PLY],
+}
+
+impl PvTable {
+    pub fn new() -> Self {
+        Self {
+            moves: vec![[Move::NULL; MAX_PLY]; MAX_PLY],
+            lengths: [0; MAX_PLY],
+        }
+    }
+
+    /// Empties the line at `ply` before its children are searched.
+    pub fn clear(&mut self, ply: usize) {
+        self.lengths[ply] = 0;
+    }
+
+    /// Sets the line at `ply` to `mv` followed by the line found at `ply + 1`.
+    pub fn update(&mut self, ply: usize, mv: Move) {
+        self.moves[ply][0] = mv;
+        if ply + 1 < MAX_PLY {
+            let child_len = self.lengths[ply + 1].min(MAX_PLY - 1);
+            let (parent, child) = self.moves.split_at_mut(ply + 1);
+            parent[ply][1..=child_len].copy_from_slice(&child[0][..child_len]);
+            self.lengths[ply] = child_len + 1;
+        } else {
+            self.lengths[ply] = 1;
+        }
+    }
+
+    /// Returns the principal variation from the root.
+    pub fn line(&self) -> Vec<Move> {
+        self.moves[0][..self.lengths[0]].to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chess::{Square, movegen::MoveKind};
+
+    use super::*;
+
+    #[test]
+    fn test_update_collects_child_line() {
+        let e4 = Move::new(Square::E2, Square::E4, MoveKind::Quiet);
+        let e5 = Move::new(Square::E7, Square::E5, MoveKind::Quiet);
+        let nf3 = Move::new(Square::G1, Square::F3, MoveKind::Quiet);
+
+        let mut pv = PvTable::new();
+        pv.clear(3);
+        pv.update(2, nf3);
+        pv.update(1, e5);
+        pv.update(0, e4);
+
+        assert_eq!(vec![e4, e5, nf3], pv.line());
+    }
+
+    #[test]
+    fn test_clear_truncates_line() {
+        let e4 = Move::new(Square::E2, Square::E4, MoveKind::Quiet);
+        let e5 = Move::new(Square::E7, Square::E5, MoveKind::Quiet);
+
+        let mut pv = PvTable::new();
+        pv.update(1, e5);
+        pv.clear(1);
+        pv.update(0, e4);
+
+        assert_eq!(vec![e4], pv.line());
+    }
+}
@@ -0,0 +1,1102 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use lazy_static::lazy_static;
+
+use crate::{
+    chess::{
+        Position, State,
+        movegen::{Move, MoveKind, MoveList, generate_legal_moves},
+    },
+    eval::evaluate,
+    search::{
+        SearchLimits, SearchOptions, SearchSignals,
+        history::{MoveHistory, PieceTo},
+        movepick::{MovePicker, captured_value},
+        pv::PvTable,
+        time::TimeManager,
+        tt::{Bound, TranspositionTable, score_from_tt, score_to_tt},
+    },
+};
+
+/// Maximum number of plies the search will ever look ahead.
+pub const MAX_PLY: usize = 128;
+/// Score bound larger than any reachable score.
+pub const INFINITY: i32 = 32_000;
+/// Score of delivering checkmate at the root.
+pub const MATE: i32 = 31_000;
+/// Any score beyond this bound is a forced mate.
+pub const MATE_BOUND: i32 = MATE - MAX_PLY as i32;
+pub const DRAW: i32 = 0;
+
+/// How many nodes are searched between checks of the stop conditions.
+const CHECK_INTERVAL: u64 = 1024;
+
+/// Margin over the captured piece's value by which a capture must fall short of alpha before
+/// quiescence search skips it, allowing for positional gains the capture might bring.
+const DELTA_MARGIN: i32 = 200;
+
+/// Null move pruning searches the null move this many plies shallower, plus one more for every
+/// four plies of depth.
+const NULL_MOVE_REDUCTION: u32 = 3;
+const NULL_MOVE_MIN_DEPTH: u32 = 3;
+
+/// Reverse futility pruning assumes the side to move can lose at most this much per remaining
+/// ply before its static evaluation no longer holds beta.
+const REVERSE_FUTILITY_MARGIN: i32 = 80;
+const REVERSE_FUTILITY_MAX_DEPTH: u32 = 8;
+
+/// Razoring drops to quiescence search when the static evaluation trails alpha by this much per
+/// remaining ply.
+const RAZOR_MARGIN: i32 = 250;
+const RAZOR_MAX_DEPTH: u32 = 3;
+
+/// Futility pruning assumes a quiet move gains at most this much per remaining ply, plus one.
+const FUTILITY_MARGIN: i32 = 100;
+const FUTILITY_MAX_DEPTH: u32 = 6;
+
+const LATE_MOVE_PRUNING_MAX_DEPTH: u32 = 8;
+const LATE_MOVE_REDUCTION_MIN_DEPTH: u32 = 3;
+
+/// Singular extensions verify transposition table moves at this depth and above, stored from a
+/// search at most `SINGULAR_TT_DEPTH_MARGIN` plies shallower.
+const SINGULAR_MIN_DEPTH: u32 = 8;
+const SINGULAR_TT_DEPTH_MARGIN: u32 = 3;
+
+/// Half-width of the first aspiration window around the previous iteration's score.
+const ASPIRATION_WINDOW: i32 = 25;
+/// Shallower iterations are searched with a full window, their scores being too unstable for a
+/// narrow window to pay off.
+const ASPIRATION_MIN_DEPTH: u32 = 4;
+
+/// Size of each dimension of the late move reduction table, beyond which values repeat.
+const LMR_TABLE_SIZE: usize = 64;
+
+lazy_static! {
+    /// Plies by which late quiet moves are reduced, indexed by depth and move number. Both grow
+    /// the reduction logarithmically.
+    static ref LMR_TABLE: [[u32; LMR_TABLE_SIZE]; LMR_TABLE_SIZE] = {
+        let mut table = [[0; LMR_TABLE_SIZE]; LMR_TABLE_SIZE];
+        for (depth, row) in table.iter_mut().enumerate().skip(1) {
+            for (move_number, reduction) in row.iter_mut().enumerate().skip(1) {
+                *reduction =
+                    (0.75 + (depth as f64).ln() * (move_number as f64).ln() / 2.25) as u32;
+            }
+        }
+        table
+    };
+}
+
+/// Returns how many plies to reduce the `move_number`th move searched at `depth` by.
+fn late_move_reduction(depth: u32, move_number: u32) -> u32 {
+    let depth = (depth as usize).min(LMR_TABLE_SIZE - 1);
+    let move_number = (move_number as usize).min(LMR_TABLE_SIZE - 1);
+    LMR_TABLE[depth][move_number]
+}
+
+/// Returns how many quiet moves are searched at `depth` before late move pruning skips the rest.
+fn late_move_count(depth: u32) -> usize {
+    (3 + depth * depth) as usize
+}
+
+/// Returns whether the fifty move rule draws the position. Checkmate takes precedence, so a
+/// side in check needs a legal move for the draw.
+fn is_fifty_move_draw(position: &Position) -> bool {
+    position.half_move_clock() >= 100
+        && (!position.is_checked(position.side_to_move())
+            || !generate_legal_moves(position).is_empty())
+}
+
+/// Evaluation of a position from the side to move's perspective.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Score {
+    /// Advantage in hundredths of a pawn.
+    Centipawns(i32),
+    /// Forced mate in the given number of moves. Negative when the side to move is mated.
+    Mate(i32),
+}
+
+impl Score {
+    /// Converts an internal search score into a [`Score`].
+    pub fn from_raw(score: i32) -> Self {
+        if score >= MATE_BOUND {
+            Self::Mate((MATE - score + 1) / 2)
+        } else if score <= -MATE_BOUND {
+            Self::Mate(-(MATE + score) / 2)
+        } else {
+            Self::Centipawns(score)
+        }
+    }
+}
+
+/// Summary of a completed iterative deepening iteration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchInfo {
+    pub depth: u32,
+    pub seldepth: u32,
+    /// Nodes visited, including those of the quiescence search.
+    pub nodes: u64,
+    /// Nodes visited by the quiescence search alone.
+    pub qnodes: u64,
+    pub time: Duration,
+    pub score: Score,
+    /// Whether `score` is exact, or only a bound because it fell outside the aspiration window.
+    pub bound: Bound,
+    /// Permille of the transposition table used by this search.
+    pub hashfull: u32,
+    pub pv: Vec<Move>,
+}
+
+/// Outcome of a search.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchResult {
+    /// The best move found, or `None` if the position has no legal moves.
+    pub best_move: Option<Move>,
+    /// The expected reply to the best move, if known.
+    pub ponder_move: Option<Move>,
+    pub score: Score,
+    pub depth: u32,
+    pub nodes: u64,
+    pub qnodes: u64,
+}
+
+/// Alpha-beta searcher driven by iterative deepening.
+///
+/// Each iteration runs a [negamax](https://www.chessprogramming.org/Negamax) search with
+/// alpha-beta pruning one ply deeper than the last, resolving captures at its leaves with a
+/// [quiescence search](https://www.chessprogramming.org/Quiescence_Search). Only fully completed
+/// iterations are trusted, so a search interrupted by its limits reports the result of the
+/// previous depth.
+///
+/// Iterations start from an [aspiration window](https://www.chessprogramming.org/Aspiration_Windows)
+/// around the previous score, and within the tree every move after the first is searched with a
+/// zero window, as in [principal variation search](https://www.chessprogramming.org/Principal_Variation_Search).
+///
+/// Away from the principal variation, nodes whose static evaluation is far from the window are
+/// cut short by reverse futility pruning, razoring and
+/// [null move pruning](https://www.chessprogramming.org/Null_Move_Pruning), and late quiet
+/// moves are pruned or searched at reduced depth. Each technique can be switched off through
+/// [`SearchOptions`].
+///
+/// Checks, and transposition table moves that prove to be the only good move, are searched a ply
+/// deeper. The extensions along any path may add up to at most the depth of the iteration, so
+/// that the search of a forcing line always terminates.
+pub struct Searcher {
+    limits: SearchLimits,
+    options: SearchOptions,
+    signals: SearchSignals,
+    tt: Arc<TranspositionTable>,
+    time: TimeManager,
+    nodes: u64,
+    qnodes: u64,
+    seldepth: usize,
+    /// Depth of the current iteration.
+    root_depth: u32,
+    stopped: bool,
+    completed_depth: u32,
+    pv: PvTable,
+    /// States saved by the moves of the game and of the current search line, so repetitions
+    /// of positions from before the root are found too.
+    history: Vec<State>,
+    move_history: MoveHistory,
+    /// The piece and destination of the move played at each ply, for the move history.
+    moved: [Option<PieceTo>; MAX_PLY],
+    /// Plies of extensions along the path from the root to each ply.
+    extensions: [u32; MAX_PLY],
+    /// The move left out of the search at each ply while verifying that it is singular, or
+    /// [`Move::NULL`] when none is.
+    excluded: [Move; MAX_PLY],
+    root_moves: Vec<Move>,
+}
+
+impl Searcher {
+    pub fn new(limits: SearchLimits, signals: SearchSignals, tt: Arc<TranspositionTable>) -> Self {
+        Self {
+            time: TimeManager::new(&limits, Instant::now()),
+            limits,
+            options: SearchOptions::default(),
+            signals,
+            tt,
+            nodes: 0,
+            qnodes: 0,
+            seldepth: 0,
+            root_depth: 0,
+            stopped: false,
+            completed_depth: 0,
+            pv: PvTable::new(),
+            history: Vec::new(),
+            move_history: MoveHistory::new(),
+            moved: [None; MAX_PLY],
+            extensions: [0; MAX_PLY],
+            excluded: [Move::NULL; MAX_PLY],
+            root_moves: Vec::new(),
+        }
+    }
+
+    /// Restricts the search to the given root moves. An empty list searches all moves.
+    pub fn with_root_moves(mut self, root_moves: Vec<Move>) -> Self {
+        self.root_moves = root_moves;
+        self
+    }
+
+    /// Sets the states saved by the moves of the game leading to the root position.
+    pub fn with_history(mut self, history: Vec<State>) -> Self {
+        self.history = history;
+        self
+    }
+
+    pub fn with_options(mut self, options: SearchOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Searches `position` until a limit is reached, calling `report` after every iteration.
+    pub fn search(
+        &mut self,
+        position: &mut Position,
+        mut report: impl FnMut(&SearchInfo),
+    ) -> SearchResult {
+        self.time = TimeManager::new(&self.limits, Instant::now());
+        self.nodes = 0;
+        self.qnodes = 0;
+        self.stopped = false;
+        self.completed_depth = 0;
+        self.tt.new_search();
+
+        let max_depth = self.limits.depth.map_or(MAX_PLY as u32 - 1, |depth| {
+            depth.clamp(1, MAX_PLY as u32 - 1)
+        });
+        let mut result = SearchResult {
+            best_move: None,
+            ponder_move: None,
+            score: Score::Centipawns(DRAW),
+            depth: 0,
+            nodes: 0,
+            qnodes: 0,
+        };
+
+        let mut score = DRAW;
+        let mut pv = Vec::new();
+        for depth in 1..=max_depth {
+            self.seldepth = 0;
+            self.root_depth = depth;
+            if depth > 1 {
+                self.move_history.age();
+            }
+
+            score = self.aspiration_search(position, depth, score, &pv, &mut report);
+            if self.stopped {
+                break;
+            }
+
+            self.completed_depth = depth;
+            pv = self.pv.line();
+            let info = self.info(depth, score, Bound::Exact, pv.clone());
+            report(&info);
+
+            result = SearchResult {
+                best_move: info.pv.first().copied(),
+                ponder_move: info.pv.get(1).copied(),
+                score: info.score,
+                depth,
+                nodes: self.nodes,
+                qnodes: self.qnodes,
+            };
+
+            if let Some(best_move) = result.best_move {
+                self.time.update(best_move, score);
+            }
+
+            // Another iteration would likely not finish before the hard limit
+            if result.best_move.is_none()
+                || self.mate_limit_reached(info.score)
+                || (!self.signals.is_pondering() && self.time.soft_limit_reached())
+            {
+                break;
+            }
+        }
+
+        result.nodes = self.nodes;
+        result.qnodes = self.qnodes;
+        result
+    }
+
+    /// Searches the root with a window around the score of the previous iteration, widening it
+    /// on the side the score falls outside of until the score lands within it.
+    ///
+    /// Each failed search is reported with its bound. A fail low has no move reaching alpha, so
+    /// it is reported with the principal variation of the previous iteration.
+    fn aspiration_search(
+        &mut self,
+        position: &mut Position,
+        depth: u32,
+        previous_score: i32,
+        previous_pv: &[Move],
+        report: &mut impl FnMut(&SearchInfo),
+    ) -> i32 {
+        let mut delta = ASPIRATION_WINDOW;
+        let (mut alpha, mut beta) =
+            if depth >= ASPIRATION_MIN_DEPTH && previous_score.abs() < MATE_BOUND {
+                (previous_score - delta, previous_score + delta)
+            } else {
+                (-INFINITY, INFINITY)
+            };
+
+        loop {
+            let score = self.negamax(position, depth, 0, alpha, beta);
+            if self.stopped {
+                return score;
+            }
+
+            if score <= alpha {
+                report(&self.info(depth, score, Bound::Upper, previous_pv.to_vec()));
+                beta = (alpha + beta) / 2;
+                alpha = (score - delta).max(-INFINITY);
+            } else if score >= beta {
+                report(&self.info(depth, score, Bound::Lower, self.pv.line()));
+                beta = (score + delta).min(INFINITY);
+            } else {
+                return score;
+            }
+
+            delta += delta / 2;
+        }
+    }
+
+    fn info(&self, depth: u32, score: i32, bound: Bound, pv: Vec<Move>) -> SearchInfo {
+        SearchInfo {
+            depth,
+            seldepth: self.seldepth as u32,
+            nodes: self.nodes,
+            qnodes: self.qnodes,
+            time: self.time.elapsed(),
+            score: Score::from_raw(score),
+            bound,
+            hashfull: self.tt.hashfull(),
+            pv,
+        }
+    }
+
+    fn negamax(
+        &mut self,
+        position: &mut Position,
+        depth: u32,
+        ply: usize,
+        mut alpha: i32,
+        beta: i32,
+    ) -> i32 {
+        self.pv.clear(ply);
+        if depth == 0 {
+            return self.quiescence(position, ply, alpha, beta, true);
+        }
+
+        if self.should_stop() {
+            return DRAW;
+        }
+
+        self.nodes += 1;
+        self.seldepth = self.seldepth.max(ply);
+
+        if ply > 0 && (position.is_repetition(&self.history) || is_fifty_move_draw(position)) {
+            return DRAW;
+        }
+
+        if ply >= MAX_PLY - 1 {
+            return evaluate(position);
+        }
+
+        let original_alpha = alpha;
+        let pv_node = beta - alpha > 1;
+        let excluded = self.excluded[ply];
+        let tt_entry = self.tt.probe(position.hash());
+
+        // Principal variation nodes are searched regardless, so the full line can be collected.
+        // The entry does not apply to a search leaving out a move either.
+        if let Some(entry) = tt_entry
+            && !pv_node
+            && excluded.is_null()
+            && entry.depth as u32 >= depth
+        {
+            let score = score_from_tt(entry.score, ply);
+            let cutoff = match entry.bound {
+                Bound::Exact => true,
+                Bound::Lower => score >= beta,
+                Bound::Upper => score <= alpha,
+            };
+
+            if cutoff {
+                return score;
+            }
+        }
+
+        let us = position.side_to_move();
+        let in_check = position.is_checked(us);
+        let static_eval = if in_check {
+            -INFINITY
+        } else {
+            evaluate(position)
+        };
+
+        if !pv_node && !in_check && excluded.is_null() {
+            // The position is so good that even after losing some material it still beats beta
+            if self.options.reverse_futility_pruning
+                && depth <= REVERSE_FUTILITY_MAX_DEPTH
+                && beta.abs() < MATE_BOUND
+                && static_eval - REVERSE_FUTILITY_MARGIN * depth as i32 >= beta
+            {
+                return static_eval;
+            }
+
+            // The position is so bad that only captures could bring it back above alpha
+            if self.options.razoring
+                && depth <= RAZOR_MAX_DEPTH
+                && static_eval + RAZOR_MARGIN * depth as i32 <= alpha
+            {
+                let score = self.quiescence(position, ply, alpha, alpha + 1, true);
+                if score <= alpha {
+                    return score;
+                }
+            }
+
+            // If passing the turn still beats beta, a real move almost certainly would too. This
+            // fails in zugzwang, which is most common when only pawns are left, and two null
+            // moves in a row would merely return to the same position.
+            if self.options.null_move_pruning
+                && depth >= NULL_MOVE_MIN_DEPTH
+                && static_eval >= beta
+                && ply > 0
+                && self.moved[ply - 1].is_some()
+                && position.has_non_pawn_material(us)
+            {
+                let reduction = NULL_MOVE_REDUCTION + depth / 4;
+                self.moved[ply] = None;
+                self.extensions[ply + 1] = self.extensions[ply];
+                position.make_null_move(&mut self.history);
+                let score = -self.negamax(
+                    position,
+                    depth.saturating_sub(1 + reduction),
+                    ply + 1,
+                    -beta,
+                    -beta + 1,
+                );
+                position.unmake_null_move(&mut self.history);
+
+                if self.stopped {
+                    return DRAW;
+                }
+
+                // A mate found without moving is not trustworthy
+                if score >= beta {
+                    return if score >= MATE_BOUND { beta } else { score };
+                }
+            }
+        }
+
+        let previous = self.previous_moves(ply);
+        let can_extend = self.extensions[ply] < self.root_depth;
+        let mut picker = MovePicker::new(
+            position,
+            tt_entry.and_then(|entry| entry.best_move),
+            &self.move_history,
+            ply,
+            previous,
+        );
+
+        let mut best_score = -INFINITY;
+        let mut best_move = None;
+        let mut legal_moves = 0;
+        let mut quiets_tried = MoveList::new();
+        while let Some(mv) = picker.next(position, &self.move_history) {
+            if mv == excluded
+                || (ply == 0 && !self.root_moves.is_empty() && !self.root_moves.contains(&mv))
+            {
+                continue;
+            }
+
+            // Once a move has been searched without being mated, quiet moves can be dropped without
+            // risking a false mate or stalemate score
+            let is_quiet = matches!(mv.kind(), MoveKind::Quiet | MoveKind::Castle);
+            if ply > 0 && !in_check && is_quiet && best_score > -MATE_BOUND {
+                if self.options.late_move_pruning
+                    && depth <= LATE_MOVE_PRUNING_MAX_DEPTH
+                    && quiets_tried.len() >= late_move_count(depth)
+                {
+                    picker.skip_quiets();
+                    continue;
+                }
+
+                if self.options.futility_pruning
+                    && depth <= FUTILITY_MAX_DEPTH
+                    && static_eval + FUTILITY_MARGIN * (depth as i32 + 1) <= alpha
+                    && !position.gives_check(mv)
+                {
+                    continue;
+                }
+            }
+
+            // The transposition table move is singular if every other move falls well short of
+            // its score in a shallower search, in which case it is worth a deeper look. If another
+            // move beats beta as well, this node is all but certain to fail high instead.
+            let mut extension = 0;
+            if let Some(entry) = tt_entry
+                && can_extend
+                && ply > 0
+                && depth >= SINGULAR_MIN_DEPTH
+                && excluded.is_null()
+                && entry.best_move == Some(mv)
+                && entry.bound != Bound::Upper
+                && entry.depth as u32 + SINGULAR_TT_DEPTH_MARGIN >= depth
+            {
+                let tt_score = score_from_tt(entry.score, ply);
+                if tt_score.abs() < MATE_BOUND {
+                    let singular_beta = tt_score - 2 * depth as i32;
+                    self.excluded[ply] = mv;
+                    let score = self.negamax(
+                        position,
+                        (depth - 1) / 2,
+                        ply,
+                        singular_beta - 1,
+                        singular_beta,
+                    );
+                    self.excluded[ply] = Move::NULL;
+                    self.pv.clear(ply);
+
+                    if self.stopped {
+                        return DRAW;
+                    }
+
+                    if score < singular_beta {
+                        extension = 1;
+                    } else if singular_beta >= beta {
+                        return singular_beta;
+                    }
+                }
+            }
+
+            let moved = PieceTo::new(position, mv);
+            if !position.make_move(mv, &mut self.history) {
+                continue;
+            }
+
+            legal_moves += 1;
+            self.moved[ply] = Some(moved);
+            let gives_check = position.is_checked(position.side_to_move());
+
+            // Checks are extended so forcing lines, and the mates at their end, are followed to
+            // the end rather than cut off at the horizon
+            if gives_check && can_extend {
+                extension = 1;
+            }
+            self.extensions[ply + 1] = self.extensions[ply] + extension;
+            let new_depth = depth - 1 + extension;
+
+            // Late quiet moves are unlikely to be best, so their zero window search is reduced too
+            let reduction = if self.options.late_move_reductions
+                && depth >= LATE_MOVE_REDUCTION_MIN_DEPTH
+                && legal_moves > 1 + pv_node as u32
+                && is_quiet
+                && !in_check
+                && !gives_check
+            {
+                let reduction = late_move_reduction(depth, legal_moves);
+                let reduction = if pv_node {
+                    reduction.saturating_sub(1)
+                } else {
+                    reduction
+                };
+                reduction.min(new_depth - 1)
+            } else {
+                0
+            };
+
+            // Moves after the first are expected to fail low, which a zero window proves more
+            // cheaply. Those that beat alpha after all are searched again at full depth, then
+            // with the full window to get an exact score and line.
+            let score = if legal_moves == 1 {
+                -self.negamax(position, new_depth, ply + 1, -beta, -alpha)
+            } else {
+                let mut score =
+                    -self.negamax(position, new_depth - reduction, ply + 1, -alpha - 1, -alpha);
+                if reduction > 0 && score > alpha {
+                    score = -self.negamax(position, new_depth, ply + 1, -alpha - 1, -alpha);
+                }
+                if pv_node && score > alpha && (ply == 0 || score < beta) {
+                    score = -self.negamax(position, new_depth, ply + 1, -beta, -alpha);
+                }
+                score
+            };
+            position.unmake_move(mv, &mut self.history);
+
+            if self.stopped {
+                return DRAW;
+            }
+
+            if score > best_score {
+                best_score = score;
+                best_move = Some(mv);
+                if score > alpha {
+                    alpha = score;
+                    self.pv.update(ply, mv);
+                    if alpha >= beta {
+                        if is_quiet {
+                            self.move_history.update_quiets(
+                                position,
+                                ply,
+                                depth,
+                                mv,
+                                quiets_tried.as_slice(),
+                                previous,
+                            );
+                        }
+                        break;
+                    }
+                }
+            }
+
+            if is_quiet {
+                quiets_tried.push(mv);
+            }
+        }
+
+        if legal_moves == 0 {
+            // Leaving out the only legal move does not make the position mate or stalemate
+            return if !excluded.is_null() {
+                alpha
+            } else if in_check {
+                -MATE + ply as i32
+            } else {
+                DRAW
+            };
+        }
+
+        // The result of a search leaving out a move would be mistaken for that of the position
+        if !excluded.is_null() {
+            return best_score;
+        }
+
+        let bound = if best_score >= beta {
+            Bound::Lower
+        } else if best_score > original_alpha {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        };
+        self.tt.store(
+            position.hash(),
+            best_move,
+            score_to_tt(best_score, ply),
+            depth as u8,
+            bound,
+        );
+
+        best_score
+    }
+
+    /// Searches captures until the position is quiet, so that leaves are not evaluated in the
+    /// middle of an exchange.
+    ///
+    /// The side to move may stand pat on the static evaluation instead of capturing. That is not
+    /// an option when in check, so on the first ply, where a check was delivered by the full
+    /// search, every evasion is searched instead, which also finds mates on the horizon. Deeper
+    /// plies only search captures and ignore checks.
+    fn quiescence(
+        &mut self,
+        position: &mut Position,
+        ply: usize,
+        mut alpha: i32,
+        beta: i32,
+        first_ply: bool,
+    ) -> i32 {
+        if self.should_stop() {
+            return DRAW;
+        }
+
+        self.nodes += 1;
+        self.qnodes += 1;
+        self.seldepth = self.seldepth.max(ply);
+
+        if is_fifty_move_draw(position) {
+            return DRAW;
+        }
+
+        if ply >= MAX_PLY - 1 {
+            return evaluate(position);
+        }
+
+        let in_check = first_ply && position.is_checked(position.side_to_move());
+        let mut best_score = -INFINITY;
+        let mut stand_pat = -INFINITY;
+        if !in_check {
+            stand_pat = evaluate(position);
+            if stand_pat >= beta {
+                return stand_pat;
+            }
+
+            alpha = alpha.max(stand_pat);
+            best_score = stand_pat;
+        }
+
+        // Outside of check the picker skips captures that lose material once the exchange is
+        // played out
+        let mut picker = MovePicker::quiescence(in_check);
+        let mut legal_moves = 0;
+        while let Some(mv) = picker.next(position, &self.move_history) {
+            // Skip captures that cannot raise alpha even if they win the piece for free
+            if !in_check
+                && !matches!(mv.kind(), MoveKind::Promotion(_))
+                && stand_pat + captured_value(position, mv) + DELTA_MARGIN <= alpha
+            {
+                continue;
+            }
+
+            if !position.make_move(mv, &mut self.history) {
+                continue;
+            }
+
+            legal_moves += 1;
+            let score = -self.quiescence(position, ply + 1, -beta, -alpha, false);
+            position.unmake_move(mv, &mut self.history);
+
+            if self.stopped {
+                return DRAW;
+            }
+
+            if score > best_score {
+                best_score = score;
+                if score > alpha {
+                    alpha = score;
+                    if alpha >= beta {
+                        break;
+                    }
+                }
+            }
+        }
+
+        if in_check && legal_moves == 0 {
+            return -MATE + ply as i32;
+        }
+
+        best_score
+    }
+
+    /// Polls the stop conditions, latching `stopped` once any of them is met.
+    fn should_stop(&mut self) -> bool {
+        if self.stopped {
+            return true;
+        }
+
+        if !self.nodes.is_multiple_of(CHECK_INTERVAL) {
+            return false;
+        }
+
+        // Without a completed first iteration there would be no move to report
+        if self.completed_depth == 0 {
+            return false;
+        }
+
+        self.stopped = self.signals.is_stopped()
+            || (!self.signals.is_pondering()
+                && (self.limits.nodes.is_some_and(|nodes| self.nodes >= nodes)
+                    || self.time.hard_limit_reached()));
+
+        self.stopped
+    }
+
+    /// Returns the moves played one and two plies before `ply`, if any.
+    fn previous_moves(&self, ply: usize) -> [Option<PieceTo>; 2] {
+        [
+            ply.checked_sub(1).and_then(|ply| self.moved[ply]),
+            ply.checked_sub(2).and_then(|ply| self.moved[ply]),
+        ]
+    }
+
+    fn mate_limit_reached(&self, score: Score) -> bool {
+        match (self.limits.mate, score) {
+            (Some(limit), Score::Mate(moves)) => moves > 0 && moves as u32 <= limit,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        chess::{Game, Square, movegen::MoveKind},
+        search::Clock,
+    };
+
+    use super::*;
+
+    fn searcher(limits: SearchLimits) -> Searcher {
+        Searcher::new(
+            limits,
+            SearchSignals::default(),
+            Arc::new(TranspositionTable::new(1)),
+        )
+    }
+
+    const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+    fn search(fen: &str, limits: SearchLimits) -> SearchResult {
+        search_with_options(fen, limits, SearchOptions::default())
+    }
+
+    fn search_with_options(
+        fen: &str,
+        limits: SearchLimits,
+        options: SearchOptions,
+    ) -> SearchResult {
+        let mut position: Position = fen.parse().unwrap();
+        searcher(limits)
+            .with_options(options)
+            .search(&mut position, |_| ())
+    }
+
+    fn without_selectivity() -> SearchOptions {
+        let mut options = SearchOptions::default();
+        for (_, enabled) in options.toggles() {
+            *enabled = false;
+        }
+        options
+    }
+
+    #[test]
+    fn test_finds_mate_in_one() {
+        let result = search("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", SearchLimits::depth(3));
+
+        assert_eq!(
+            Some(Move::new(Square::A1, Square::A8, MoveKind::Quiet)),
+            result.best_move
+        );
+        assert_eq!(Score::Mate(1), result.score);
+    }
+
+    #[test]
+    fn test_checkmated_has_no_moves() {
+        let result = search("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1", SearchLimits::depth(2));
+
+        assert_eq!(None, result.best_move);
+        assert_eq!(Score::Mate(0), result.score);
+    }
+
+    #[test]
+    fn test_stalemate_has_no_moves() {
+        let result = search("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1", SearchLimits::depth(2));
+
+        assert_eq!(None, result.best_move);
+        assert_eq!(Score::Centipawns(DRAW), result.score);
+    }
+
+    #[test]
+    fn test_repetition_saves_lost_position() {
+        let fen = "8/1q5k/8/8/8/8/7K/6N1 w - - 0 1";
+        let lost = search(fen, SearchLimits::depth(4));
+        assert!(matches!(lost.score, Score::Centipawns(score) if score < -500));
+
+        // After Nf3 Qa7 Ng1 Qb7, playing Nf3 again repeats the position
+        let mut position: Position = fen.parse().unwrap();
+        let mut history = Vec::new();
+        for uci in ["g1f3", "b7a7", "f3g1", "a7b7"] {
+            let mv = Move::from_uci(uci, &position).unwrap();
+            assert!(position.make_move(mv, &mut history));
+        }
+        let result = searcher(SearchLimits::depth(4))
+            .with_history(history)
+            .search(&mut position, |_| ());
+
+        assert_eq!(
+            Some(Move::new(Square::G1, Square::F3, MoveKind::Quiet)),
+            result.best_move
+        );
+        assert_eq!(Score::Centipawns(DRAW), result.score);
+    }
+
+    #[test]
+    fn test_mate_takes_precedence_over_fifty_move_rule() {
+        let result = search("4k3/8/4K3/8/8/8/8/R7 w - - 99 80", SearchLimits::depth(2));
+
+        assert_eq!(
+            Some(Move::new(Square::A1, Square::A8, MoveKind::Quiet)),
+            result.best_move
+        );
+        assert_eq!(Score::Mate(1), result.score);
+    }
+
+    #[test]
+    fn test_wins_hanging_queen() {
+        let result = search(
+            "rnb1kbnr/pppp1ppp/8/4p1q1/3P4/8/PPP1PPPP/RNBQKBNR w KQkq - 0 1",
+            SearchLimits::depth(2),
+        );
+
+        assert_eq!(
+            Some(Move::new(Square::C1, Square::G5, MoveKind::Capture)),
+            result.best_move
+        );
+    }
+
+    #[test]
+    fn test_quiescence_sees_recapture() {
+        let result = search("4k3/8/4p3/3p4/8/8/8/3QK3 w - - 0 1", SearchLimits::depth(1));
+
+        assert_ne!(
+            Some(Move::new(Square::D1, Square::D5, MoveKind::Capture)),
+            result.best_move
+        );
+        assert!(result.qnodes > 0 && result.qnodes < result.nodes);
+    }
+
+    #[test]
+    fn test_quiescence_finds_mate_on_horizon() {
+        let result = search("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", SearchLimits::depth(1));
+
+        assert_eq!(Score::Mate(1), result.score);
+    }
+
+    #[test]
+    fn test_check_extensions_find_mate_beyond_depth() {
+        // Nh6+ Kh8 Qg8+ Rxg8 Nf7#, five plies deep
+        let result = search_with_options(
+            "5rk1/5Npp/8/8/8/1Q6/8/6K1 w - - 0 1",
+            SearchLimits::depth(3),
+            without_selectivity(),
+        );
+
+        assert_eq!(Score::Mate(3), result.score);
+    }
+
+    #[test]
+    fn test_selective_search_visits_fewer_nodes() {
+        let full = search_with_options(KIWIPETE, SearchLimits::depth(5), without_selectivity());
+        let selective = search(KIWIPETE, SearchLimits::depth(5));
+
+        assert!(selective.nodes < full.nodes / 2);
+    }
+
+    #[test]
+    fn test_each_technique_keeps_tactics() {
+        for index in 0..SearchOptions::default().toggles().len() {
+            let mut options = without_selectivity();
+            let (name, enabled) = options.toggles().into_iter().nth(index).unwrap();
+            *enabled = true;
+
+            let mate = search_with_options(
+                "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1",
+                SearchLimits::depth(5),
+                options,
+            );
+            assert_eq!(Score::Mate(1), mate.score, "{name}");
+
+            let queen = search_with_options(
+                "rnb1kbnr/pppp1ppp/8/4p1q1/3P4/8/PPP1PPPP/RNBQKBNR w KQkq - 0 1",
+                SearchLimits::depth(5),
+                options,
+            );
+            assert_eq!(
+                Some(Move::new(Square::C1, Square::G5, MoveKind::Capture)),
+                queen.best_move,
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_aspiration_windows_report_bounds() {
+        let mut infos = Vec::new();
+        let mut position: Position = KIWIPETE.parse().unwrap();
+        searcher(SearchLimits::depth(6)).search(&mut position, |info| infos.push(info.clone()));
+
+        assert!(infos.iter().any(|info| info.bound != Bound::Exact));
+        for depth in 1..=6 {
+            let last = infos.iter().rfind(|info| info.depth == depth).unwrap();
+            assert_eq!(Bound::Exact, last.bound);
+        }
+    }
+
+    #[test]
+    fn test_reports_full_principal_variation() {
+        let mut infos = Vec::new();
+        let mut position = Position::default();
+        searcher(SearchLimits::depth(6)).search(&mut position, |info| infos.push(info.clone()));
+
+        for info in infos.iter().filter(|info| info.bound == Bound::Exact) {
+            assert_eq!(info.depth as usize, info.pv.len());
+
+            let mut game = Game::new(position.clone());
+            for &mv in &info.pv {
+                assert!(game.push_move(mv), "{mv:?} in {:?}", info.pv);
+            }
+        }
+    }
+
+    #[test]
+    fn test_respects_depth_limit() {
+        let mut depths = Vec::new();
+        let mut position = Position::default();
+        let result =
+            searcher(SearchLimits::depth(3)).search(&mut position, |info| depths.push(info.depth));
+
+        assert_eq!(vec![1, 2, 3], depths);
+        assert_eq!(3, result.depth);
+    }
+
+    #[test]
+    fn test_respects_node_limit() {
+        let result = search(KIWIPETE, SearchLimits::nodes(5_000));
+
+        assert!(result.best_move.is_some());
+        assert!(result.nodes < 5_000 + CHECK_INTERVAL);
+    }
+
+    #[test]
+    fn test_respects_clock() {
+        let start = Instant::now();
+        let result = search(
+            KIWIPETE,
+            SearchLimits::clock(Clock {
+                time: Duration::from_secs(3),
+                increment: Duration::ZERO,
+                moves_to_go: None,
+            }),
+        );
+
+        // The hard limit is a tenth of the clock
+        assert!(result.best_move.is_some());
+        assert!(start.elapsed() < Duration::from_millis(600));
+    }
+
+    #[test]
+    fn test_restricts_root_moves() {
+        let mut position = Position::default();
+        let root_move = Move::new(Square::A2, Square::A3, MoveKind::Quiet);
+        let result = searcher(SearchLimits::depth(2))
+            .with_root_moves(vec![root_move])
+            .search(&mut position, |_| ());
+
+        assert_eq!(Some(root_move), result.best_move);
+    }
+
+    #[test]
+    fn test_fills_transposition_table() {
+        let tt = Arc::new(TranspositionTable::new(1));
+        let mut position = Position::default();
+        let mut hashfull = 0;
+        let result = Searcher::new(SearchLimits::depth(4), SearchSignals::default(), tt.clone())
+            .search(&mut position, |info| hashfull = info.hashfull);
+
+        let entry = tt.probe(position.hash()).expect("root not stored");
+        assert_eq!(result.best_move, entry.best_move);
+        assert_eq!(4, entry.depth);
+        assert!(hashfull > 0);
+    }
+
+    #[test]
+    fn test_score_from_raw() {
+        assert_eq!(Score::Mate(1), Score::from_raw(MATE - 1));
+        assert_eq!(Score::Mate(2), Score::from_raw(MATE - 3));
+        assert_eq!(Score::Mate(-1), Score::from_raw(-MATE + 2));
+        assert_eq!(Score::Centipawns(150), Score::from_raw(150));
+    }
+}
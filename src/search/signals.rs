@@ -0,0 +1,41 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+
+/// Flags used to control a running search from another thread.
+///
+/// Signals are cheap to clone and every clone refers to the same underlying flags, so the
+/// front end can keep one copy while the search thread polls another.
+#[derive(Debug, Clone, Default)]
+pub struct SearchSignals {
+    stop: Arc<AtomicBool>,
+    pondering: Arc<AtomicBool>,
+}
+
+impl SearchSignals {
+    pub fn new(pondering: bool) -> Self {
+        Self {
+            stop: Arc::new(AtomicBool::new(false)),
+            pondering: Arc::new(AtomicBool::new(pondering)),
+        }
+    }
+
+    /// Requests that the search stop as soon as possible.
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stop.load(Ordering::Relaxed)
+    }
+
+    /// Signals that the opponent played the expected move, so the search is no longer pondering.
+    pub fn ponderhit(&self) {
+        self.pondering.store(false, Ordering::Relaxed);
+    }
+
+    pub fn is_pondering(&self) -> bool {
+        self.pondering.load(Ordering::Relaxed)
+    }
+}
@@ -0,0 +1,202 @@
+use std::time::{Duration, Instant};
+
+use crate::{chess::movegen::Move, search::SearchLimits};
+
+/// Moves assumed to remain in the game when the GUI does not send `movestogo`.
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+
+/// The hard limit allows this many times the time of the soft limit.
+const HARD_LIMIT_FACTOR: u32 = 3;
+
+/// Percentage of the clock that a single move may use at most, keeping a reserve for the moves
+/// after it.
+const MAX_CLOCK_USAGE_PERCENT: u32 = 80;
+
+/// Soft limit scales by how many iterations in a row ended with the same best move, from a move
+/// that just changed to one that has stood for several iterations.
+const STABILITY_SCALES: [f64; 5] = [1.6, 1.3, 1.1, 1.0, 0.85];
+
+/// Drop in score, in centipawns, from one iteration to the next at which the soft limit is
+/// doubled. Smaller drops extend it proportionally.
+const MAX_SCORE_DROP: i32 = 100;
+
+/// Decides how long a search may run.
+///
+/// The hard limit is checked throughout the search and stops it wherever it is. The soft limit
+/// is only checked between iterations, as it is not worth starting an iteration that is unlikely
+/// to finish. It starts from this move's share of the clock and is rescaled after every
+/// iteration: extended while the best move keeps changing or the score drops, as the search has
+/// not settled yet, and shortened once the best move has held for a few iterations.
+///
+/// A fixed move time sets both limits to that time. Without a move time or clock there are no
+/// limits at all.
+pub(crate) struct TimeManager {
+    start: Instant,
+    soft_limit: Option<Duration>,
+    hard_limit: Option<Duration>,
+    best_move: Option<Move>,
+    /// Number of iterations in a row, besides the first, that ended with `best_move`.
+    stability: usize,
+    previous_score: Option<i32>,
+    /// Factor applied to the soft limit.
+    scale: f64,
+}
+
+impl TimeManager {
+    pub fn new(limits: &SearchLimits, start: Instant) -> Self {
+        let overhead = limits.move_overhead;
+        let (soft_limit, hard_limit) = if let Some(move_time) = limits.move_time {
+            let time = move_time.saturating_sub(overhead);
+            (Some(time), Some(time))
+        } else if let Some(clock) = limits.clock {
+            let available = clock.time.saturating_sub(overhead);
+            let max_time = available * MAX_CLOCK_USAGE_PERCENT / 100;
+            let moves_to_go = clock.moves_to_go.unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+            let soft_limit = (available / moves_to_go + clock.increment * 3 / 4).min(max_time);
+            let hard_limit = (soft_limit * HARD_LIMIT_FACTOR).min(max_time);
+            (Some(soft_limit), Some(hard_limit))
+        } else {
+            (None, None)
+        };
+
+        Self {
+            start,
+            soft_limit,
+            hard_limit,
+            best_move: None,
+            stability: 0,
+            previous_score: None,
+            scale: 1.0,
+        }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    pub fn hard_limit_reached(&self) -> bool {
+        self.hard_limit.is_some_and(|limit| self.elapsed() >= limit)
+    }
+
+    pub fn soft_limit_reached(&self) -> bool {
+        self.soft_limit
+            .is_some_and(|limit| self.elapsed() >= limit.mul_f64(self.scale))
+    }
+
+    /// Rescales the soft limit by the best move and score of a completed iteration.
+    pub fn update(&mut self, best_move: Move, score: i32) {
+        if self.best_move == Some(best_move) {
+            self.stability += 1;
+        } else {
+            self.stability = 0;
+        }
+
+        let drop = self
+            .previous_score
+            .map_or(0, |previous| previous - score)
+            .clamp(0, MAX_SCORE_DROP);
+        self.best_move = Some(best_move);
+        self.previous_score = Some(score);
+        self.scale = STABILITY_SCALES[self.stability.min(STABILITY_SCALES.len() - 1)]
+            * (1.0 + drop as f64 / MAX_SCORE_DROP as f64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        chess::{Square, movegen::MoveKind},
+        search::Clock,
+    };
+
+    use super::*;
+
+    fn clock(time: u64, increment: u64, moves_to_go: Option<u32>) -> SearchLimits {
+        SearchLimits::clock(Clock {
+            time: Duration::from_millis(time),
+            increment: Duration::from_millis(increment),
+            moves_to_go,
+        })
+    }
+
+    fn limits(limits: &SearchLimits) -> (Option<Duration>, Option<Duration>) {
+        let time = TimeManager::new(limits, Instant::now());
+        (time.soft_limit, time.hard_limit)
+    }
+
+    #[test]
+    fn test_limits_from_clock() {
+        let ms = |millis| Some(Duration::from_millis(millis));
+
+        assert_eq!((ms(2_000), ms(6_000)), limits(&clock(60_000, 0, None)));
+        assert_eq!((ms(2_750), ms(8_250)), limits(&clock(60_000, 1_000, None)));
+        assert_eq!((ms(6_000), ms(18_000)), limits(&clock(60_000, 0, Some(10))));
+
+        // A single move to go may use most, but not all, of the clock
+        assert_eq!((ms(48_000), ms(48_000)), limits(&clock(60_000, 0, Some(1))));
+        assert_eq!((ms(800), ms(800)), limits(&clock(1_000, 5_000, None)));
+    }
+
+    #[test]
+    fn test_move_overhead() {
+        let mut limits = clock(3_010, 0, None);
+        limits.move_overhead = Duration::from_millis(10);
+        assert_eq!(Some(Duration::from_millis(100)), self::limits(&limits).0);
+
+        let mut limits = SearchLimits::move_time(Duration::from_millis(500));
+        limits.move_overhead = Duration::from_millis(50);
+        let expected = Some(Duration::from_millis(450));
+        assert_eq!((expected, expected), self::limits(&limits));
+
+        // The overhead may exceed the time left
+        limits.move_overhead = Duration::from_secs(1);
+        assert_eq!(Some(Duration::ZERO), self::limits(&limits).1);
+    }
+
+    #[test]
+    fn test_no_limits() {
+        let time = TimeManager::new(&SearchLimits::depth(5), Instant::now());
+        assert_eq!((None, None), (time.soft_limit, time.hard_limit));
+        assert!(!time.soft_limit_reached());
+        assert!(!time.hard_limit_reached());
+    }
+
+    #[test]
+    fn test_soft_limit_scaling() {
+        let e4 = Move::new(Square::E2, Square::E4, MoveKind::Quiet);
+        let d4 = Move::new(Square::D2, Square::D4, MoveKind::Quiet);
+        let mut time = TimeManager::new(&clock(60_000, 0, None), Instant::now());
+
+        time.update(e4, 30);
+        let unstable = time.scale;
+        for _ in 0..5 {
+            time.update(e4, 30);
+        }
+        let stable = time.scale;
+        assert!(stable < 1.0 && 1.0 < unstable);
+
+        time.update(d4, 30);
+        assert_eq!(unstable, time.scale);
+
+        time.update(d4, -20);
+        assert!(time.scale > STABILITY_SCALES[1]);
+        time.update(d4, -1_000);
+        assert_eq!(STABILITY_SCALES[2] * 2.0, time.scale);
+    }
+
+    #[test]
+    fn test_limits_elapse() {
+        let start = Instant::now() - Duration::from_millis(2_500);
+        let mut time = TimeManager::new(&clock(60_000, 0, None), start);
+        assert!(!time.hard_limit_reached());
+
+        // A new best move extends the soft limit past the elapsed time, until it has held for a
+        // few iterations
+        let e4 = Move::new(Square::E2, Square::E4, MoveKind::Quiet);
+        time.update(e4, 30);
+        assert!(!time.soft_limit_reached());
+        time.update(e4, 30);
+        time.update(e4, 30);
+        assert!(time.soft_limit_reached());
+    }
+}
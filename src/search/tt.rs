@@ -0,0 +1,350 @@
+use std::{
+    mem::size_of,
+    sync::atomic::{AtomicU8, AtomicU64, Ordering},
+};
+
+use crate::{chess::movegen::Move, search::MATE_BOUND};
+
+/// Number of entries sharing a bucket, sized so a bucket fills one 64 byte cache line.
+const BUCKET_SIZE: usize = 4;
+
+/// Number of buckets sampled when estimating how full the table is.
+const HASHFULL_SAMPLE: usize = 250;
+
+/// Ages are stored in 6 bits and wrap around.
+const AGE_MASK: u8 = 0x3f;
+
+/// Default table size in megabytes.
+pub const DEFAULT_HASH_MB: usize = 16;
+
+/// Range of table sizes, in megabytes, that can be requested.
+pub const MIN_HASH_MB: usize = 1;
+pub const MAX_HASH_MB: usize = 65536;
+
+/// How a stored score relates to the true score of a position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Bound {
+    /// The score is exact.
+    Exact = 1,
+    /// The search failed high, so the true score is at least the stored score.
+    Lower = 2,
+    /// The search failed low, so the true score is at most the stored score.
+    Upper = 3,
+}
+
+impl Bound {
+    fn from_bits(bits: u64) -> Option<Self> {
+        match bits {
+            1 => Some(Self::Exact),
+            2 => Some(Self::Lower),
+            3 => Some(Self::Upper),
+            _ => None,
+        }
+    }
+}
+
+/// Result of a successful table lookup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TtEntry {
+    pub best_move: Option<Move>,
+    pub score: i32,
+    pub depth: u8,
+    pub bound: Bound,
+}
+
+/// A single table slot.
+///
+/// The payload is packed into one word and the key is stored XORed with it, so a slot torn by a
+/// concurrent write fails verification instead of returning another position's data.
+#[derive(Default)]
+struct Slot {
+    key: AtomicU64,
+    data: AtomicU64,
+}
+
+/// Unpacked form of a slot's payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SlotData {
+    /// The best move, or [`Move::NULL`] if none was found.
+    best_move: Move,
+    score: i16,
+    depth: u8,
+    bound: Option<Bound>,
+    age: u8,
+}
+
+impl SlotData {
+    fn pack(&self) -> u64 {
+        self.best_move.to_bits() as u64
+            | (self.score as u16 as u64) << 16
+            | (self.depth as u64) << 32
+            | (self.bound.map_or(0, |bound| bound as u64)) << 40
+            | ((self.age & AGE_MASK) as u64) << 42
+    }
+
+    fn unpack(data: u64) -> Self {
+        Self {
+            best_move: Move::from_bits(data as u16),
+            score: (data >> 16) as u16 as i16,
+            depth: (data >> 32) as u8,
+            bound: Bound::from_bits(data >> 40 & 0x3),
+            age: (data >> 42) as u8 & AGE_MASK,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Bucket {
+    slots: [Slot; BUCKET_SIZE],
+}
+
+/// Shared cache of search results keyed by position hash.
+///
+/// Entries are grouped into buckets of [`BUCKET_SIZE`]. On a collision the entry that is least
+/// valuable to keep is replaced, preferring entries from earlier searches and then shallower
+/// entries. All operations take `&self` so the table can be shared between search threads.
+pub struct TranspositionTable {
+    buckets: Vec<Bucket>,
+    age: AtomicU8,
+}
+
+impl TranspositionTable {
+    /// Creates a table using roughly `size_mb` megabytes of memory.
+    pub fn new(size_mb: usize) -> Self {
+        let size_mb = size_mb.clamp(MIN_HASH_MB, MAX_HASH_MB);
+        let num_buckets = (size_mb * 1024 * 1024 / size_of::<Bucket>()).max(1);
+
+        Self {
+            buckets: (0..num_buckets).map(|_| Bucket::default()).collect(),
+            age: AtomicU8::new(0),
+        }
+    }
+
+    /// Removes every entry from the table.
+    pub fn clear(&self) {
+        for slot in self.buckets.iter().flat_map(|bucket| &bucket.slots) {
+            slot.key.store(0, Ordering::Relaxed);
+            slot.data.store(0, Ordering::Relaxed);
+        }
+
+        self.age.store(0, Ordering::Relaxed);
+    }
+
+    /// Marks the start of a new search, so entries from earlier searches are replaced first.
+    pub fn new_search(&self) {
+        let age = self.age.load(Ordering::Relaxed);
+        self.age.store((age + 1) & AGE_MASK, Ordering::Relaxed);
+    }
+
+    /// Looks up the entry stored for `hash`.
+    pub fn probe(&self, hash: u64) -> Option<TtEntry> {
+        self.bucket(hash).slots.iter().find_map(|slot| {
+            let data = slot.data.load(Ordering::Relaxed);
+            if slot.key.load(Ordering::Relaxed) ^ data != hash {
+                return None;
+            }
+
+            let data = SlotData::unpack(data);
+            Some(TtEntry {
+                best_move: (!data.best_move.is_null()).then_some(data.best_move),
+                score: data.score as i32,
+                depth: data.depth,
+                bound: data.bound?,
+            })
+        })
+    }
+
+    /// Stores a search result for `hash`.
+    pub fn store(&self, hash: u64, best_move: Option<Move>, score: i32, depth: u8, bound: Bound) {
+        let age = self.age.load(Ordering::Relaxed);
+        let bucket = self.bucket(hash);
+
+        let mut replace = &bucket.slots[0];
+        let mut replace_value = i32::MAX;
+        for slot in bucket.slots.iter() {
+            let data = slot.data.load(Ordering::Relaxed);
+            if slot.key.load(Ordering::Relaxed) ^ data == hash {
+                replace = slot;
+                break;
+            }
+
+            let data = SlotData::unpack(data);
+            let relative_age = (age.wrapping_sub(data.age) & AGE_MASK) as i32;
+            let value = data.depth as i32 - 8 * relative_age;
+            if data.bound.is_none() || value < replace_value {
+                replace = slot;
+                replace_value = if data.bound.is_none() {
+                    i32::MIN
+                } else {
+                    value
+                };
+            }
+        }
+
+        // Keep the previous best move when re-searching a position that has not found one
+        let old_data = replace.data.load(Ordering::Relaxed);
+        let best_move = match best_move {
+            None if replace.key.load(Ordering::Relaxed) ^ old_data == hash => {
+                SlotData::unpack(old_data).best_move
+            }
+            _ => best_move.unwrap_or(Move::NULL),
+        };
+
+        let data = SlotData {
+            best_move,
+            score: score as i16,
+            depth,
+            bound: Some(bound),
+            age,
+        }
+        .pack();
+        replace.key.store(hash ^ data, Ordering::Relaxed);
+        replace.data.store(data, Ordering::Relaxed);
+    }
+
+    /// Estimates, in permille, how much of the table is used by the current search.
+    pub fn hashfull(&self) -> u32 {
+        let age = self.age.load(Ordering::Relaxed);
+        let sample = &self.buckets[..HASHFULL_SAMPLE.min(self.buckets.len())];
+        let used = sample
+            .iter()
+            .flat_map(|bucket| &bucket.slots)
+            .filter(|slot| {
+                let data = SlotData::unpack(slot.data.load(Ordering::Relaxed));
+                data.bound.is_some() && data.age == age
+            })
+            .count();
+
+        (used * 1000 / (sample.len() * BUCKET_SIZE)) as u32
+    }
+
+    fn bucket(&self, hash: u64) -> &Bucket {
+        // Maps the hash onto the bucket range without a division
+        let index = (hash as u128 * self.buckets.len() as u128) >> 64;
+        &self.buckets[index as usize]
+    }
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        Self::new(DEFAULT_HASH_MB)
+    }
+}
+
+/// Converts a score relative to the root into one relative to the node at `ply` for storage.
+///
+/// Mate scores count plies from the root, but the same position can be reached at different
+/// plies, so they are stored as a distance from the node itself.
+pub(crate) fn score_to_tt(score: i32, ply: usize) -> i32 {
+    if score >= MATE_BOUND {
+        score + ply as i32
+    } else if score <= -MATE_BOUND {
+        score - ply as i32
+    } else {
+        score
+    }
+}
+
+/// Inverse of [`score_to_tt`].
+pub(crate) fn score_from_tt(score: i32, ply: usize) -> i32 {
+    if score >= MATE_BOUND {
+        score - ply as i32
+    } else if score <= -MATE_BOUND {
+        score + ply as i32
+    } else {
+        score
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chess::{Square, movegen::MoveKind};
+
+    use super::*;
+
+    #[test]
+    fn test_bucket_fills_cache_line() {
+        assert_eq!(64, size_of::<Bucket>());
+    }
+
+    #[test]
+    fn test_store_and_probe() {
+        let tt = TranspositionTable::new(1);
+        let mv = Move::new(Square::G1, Square::F3, MoveKind::Quiet);
+        tt.store(0xdead_beef, Some(mv), -250, 7, Bound::Lower);
+
+        let entry = tt.probe(0xdead_beef).expect("entry not found");
+        assert_eq!(Some(mv), entry.best_move);
+        assert_eq!(-250, entry.score);
+        assert_eq!(7, entry.depth);
+        assert_eq!(Bound::Lower, entry.bound);
+
+        assert_eq!(None, tt.probe(0xdead_beee));
+    }
+
+    #[test]
+    fn test_store_keeps_move_without_replacement() {
+        let tt = TranspositionTable::new(1);
+        let mv = Move::new(Square::G1, Square::F3, MoveKind::Quiet);
+        tt.store(42, Some(mv), 10, 3, Bound::Exact);
+        tt.store(42, None, -10, 4, Bound::Upper);
+
+        let entry = tt.probe(42).unwrap();
+        assert_eq!(Some(mv), entry.best_move);
+        assert_eq!(4, entry.depth);
+        assert_eq!(Bound::Upper, entry.bound);
+    }
+
+    #[test]
+    fn test_replaces_stale_entries_first() {
+        let tt = TranspositionTable::new(1);
+        // Every hash below 2^64 / num_buckets lands in the first bucket
+        let hashes: Vec<u64> = (1..=BUCKET_SIZE as u64 + 1).collect();
+        for &hash in &hashes[..BUCKET_SIZE] {
+            tt.store(hash, None, 0, 10, Bound::Exact);
+        }
+
+        tt.new_search();
+        tt.store(hashes[BUCKET_SIZE], None, 0, 1, Bound::Exact);
+
+        assert!(tt.probe(hashes[BUCKET_SIZE]).is_some());
+        assert_eq!(
+            BUCKET_SIZE - 1,
+            hashes[..BUCKET_SIZE]
+                .iter()
+                .filter(|&&hash| tt.probe(hash).is_some())
+                .count()
+        );
+    }
+
+    #[test]
+    fn test_clear_and_hashfull() {
+        let tt = TranspositionTable::new(1);
+        assert_eq!(0, tt.hashfull());
+
+        for hash in 0..100_000u64 {
+            tt.store(
+                hash.wrapping_mul(0x9e37_79b9_7f4a_7c15),
+                None,
+                0,
+                1,
+                Bound::Exact,
+            );
+        }
+        assert!(tt.hashfull() > 0);
+
+        tt.clear();
+        assert_eq!(0, tt.hashfull());
+        assert_eq!(None, tt.probe(0x9e37_79b9_7f4a_7c15));
+    }
+
+    #[test]
+    fn test_mate_scores_are_node_relative() {
+        let score = MATE_BOUND + 10;
+        assert_eq!(score + 5, score_to_tt(score, 5));
+        assert_eq!(score, score_from_tt(score_to_tt(score, 5), 5));
+        assert_eq!(-score, score_from_tt(score_to_tt(-score, 3), 3));
+        assert_eq!(100, score_to_tt(100, 5));
+    }
+}
@@ -0,0 +1,664 @@
+use std::{
+    io::{self, BufRead},
+    str::{FromStr, SplitWhitespace},
+    sync::Arc,
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use thiserror::Error;
+
+use crate::{
+    chess::{self, Color, Position, STARTING_FEN, State, movegen::Move},
+    eval::evaluate,
+    search::{
+        Bound, Clock, DEFAULT_HASH_MB, MAX_HASH_MB, MIN_HASH_MB, Score, SearchInfo, SearchLimits,
+        SearchOptions, SearchSignals, Searcher, TranspositionTable,
+    },
+};
+
+const ENGINE_NAME: &str = "Athena";
+const ENGINE_AUTHOR: &str = "the Athena developers";
+
+const DEFAULT_MOVE_OVERHEAD_MS: u64 = 10;
+const MAX_MOVE_OVERHEAD_MS: u64 = 5_000;
+
+/// How long an idle search thread sleeps between checks of the stop and ponder flags.
+const IDLE_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+#[derive(Debug, Error)]
+pub enum UciError {
+    #[error("Unknown command: {0}")]
+    UnknownCommand(String),
+    #[error("Missing value for '{0}'")]
+    MissingValue(String),
+    #[error("Invalid value for '{0}': {1}")]
+    InvalidValue(String, String),
+    #[error("Invalid FEN '{0}': {1}")]
+    InvalidFen(String, chess::Error),
+    #[error("{0}")]
+    IllegalMove(chess::Error),
+    #[error("No such option: {0}")]
+    UnknownOption(String),
+}
+
+/// Parameters of a UCI `go` command.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GoParams {
+    pub search_moves: Vec<Move>,
+    pub ponder: bool,
+    pub white_time: Option<Duration>,
+    pub black_time: Option<Duration>,
+    pub white_increment: Option<Duration>,
+    pub black_increment: Option<Duration>,
+    pub moves_to_go: Option<u32>,
+    pub depth: Option<u32>,
+    pub nodes: Option<u64>,
+    pub mate: Option<u32>,
+    pub move_time: Option<Duration>,
+    pub infinite: bool,
+}
+
+impl GoParams {
+    /// Converts the `go` parameters into limits for a search by `side`.
+    fn limits(&self, side: Color) -> SearchLimits {
+        let (time, increment) = if side.is_white() {
+            (self.white_time, self.white_increment)
+        } else {
+            (self.black_time, self.black_increment)
+        };
+
+        SearchLimits {
+            depth: self.depth,
+            nodes: self.nodes,
+            move_time: self.move_time,
+            clock: time.map(|time| Clock {
+                time,
+                increment: increment.unwrap_or_default(),
+                moves_to_go: self.moves_to_go,
+            }),
+            move_overhead: Duration::ZERO,
+            mate: self.mate,
+        }
+    }
+}
+
+/// A search running on a background thread.
+struct SearchHandle {
+    signals: SearchSignals,
+    thread: JoinHandle<()>,
+}
+
+impl SearchHandle {
+    fn stop(self) {
+        self.signals.stop();
+        self.thread.join().expect("search thread panicked");
+    }
+}
+
+/// Universal Chess Interface front end.
+///
+/// Reads [UCI](https://www.chessprogramming.org/UCI) commands line by line and drives the engine
+/// accordingly. Searches run on a background thread so that `stop`, `ponderhit` and `isready`
+/// can be answered while the engine is thinking. All engine output is written to stdout.
+pub struct Uci {
+    position: Position,
+    history: Vec<State>,
+    /// Whether the GUI plays Chess960 and writes castling as the king taking its own rook.
+    chess960: bool,
+    search: Option<SearchHandle>,
+    tt: Arc<TranspositionTable>,
+    search_options: SearchOptions,
+    /// Time set aside from every move for the delay in communicating with the GUI.
+    move_overhead: Duration,
+}
+
+impl Uci {
+    pub fn new() -> Self {
+        Self {
+            position: Position::default(),
+            history: Vec::new(),
+            chess960: false,
+            search: None,
+            tt: Arc::new(TranspositionTable::default()),
+            search_options: SearchOptions::default(),
+            move_overhead: Duration::from_millis(DEFAULT_MOVE_OVERHEAD_MS),
+        }
+    }
+
+    /// Processes commands from `input` until `quit` is received or the input is exhausted.
+    pub fn run(&mut self, input: impl BufRead) -> io::Result<()> {
+        for line in input.lines() {
+            let line = line?;
+            match self.handle_command(&line) {
+                Ok(true) => (),
+                Ok(false) => break,
+                Err(e) => println!("info string {e}"),
+            }
+        }
+
+        self.stop_search();
+        Ok(())
+    }
+
+    /// Handles a single command, returning whether the engine should keep running.
+    fn handle_command(&mut self, line: &str) -> Result<bool, UciError> {
+        let mut tokens = line.split_whitespace();
+        let Some(command) = tokens.next() else {
+            return Ok(true);
+        };
+
+        match command {
+            "uci" => {
+                println!("id name {ENGINE_NAME} {}", env!("CARGO_PKG_VERSION"));
+                println!("id author {ENGINE_AUTHOR}");
+                println!(
+                    "option name Hash type spin default {DEFAULT_HASH_MB} min {MIN_HASH_MB} max {MAX_HASH_MB}"
+                );
+                println!("option name Clear Hash type button");
+                println!(
+                    "option name Move Overhead type spin default {DEFAULT_MOVE_OVERHEAD_MS} min 0 max {MAX_MOVE_OVERHEAD_MS}"
+                );
+                println!("option name Ponder type check default false");
+                println!("option name UCI_Chess960 type check default false");
+                for (name, enabled) in SearchOptions::default().toggles() {
+                    println!("option name {name} type check default {enabled}");
+                }
+                println!("uciok");
+            }
+            "isready" => println!("readyok"),
+            "ucinewgame" => {
+                self.stop_search();
+                self.position = Position::default();
+                self.position.set_chess960(self.chess960);
+                self.history.clear();
+                self.tt.clear();
+            }
+            "setoption" => self.set_option(tokens)?,
+            "position" => {
+                self.stop_search();
+                let (position, history) = parse_position(tokens, self.chess960)?;
+                self.position = position;
+                self.history = history;
+            }
+            "go" => {
+                self.stop_search();
+                let params = parse_go(tokens, &self.position)?;
+                self.start_search(params);
+            }
+            "stop" => self.stop_search(),
+            "ponderhit" => {
+                if let Some(search) = &self.search {
+                    search.signals.ponderhit();
+                }
+            }
+            "d" => {
+                print!("{}", self.position);
+                println!("Fen: {}", self.position.to_fen());
+                println!("Key: {:016x}", self.position.hash());
+            }
+            "eval" => println!("info string static eval cp {}", evaluate(&self.position)),
+            "quit" => return Ok(false),
+            _ => return Err(UciError::UnknownCommand(command.to_string())),
+        }
+
+        Ok(true)
+    }
+
+    fn set_option(&mut self, tokens: SplitWhitespace) -> Result<(), UciError> {
+        self.stop_search();
+        let (name, value) = parse_set_option(tokens)?;
+        match name.to_lowercase().as_str() {
+            "hash" => {
+                let size_mb: usize = parse_value(&name, value.as_deref())?;
+                if !(MIN_HASH_MB..=MAX_HASH_MB).contains(&size_mb) {
+                    return Err(UciError::InvalidValue(name, size_mb.to_string()));
+                }
+
+                // Release the old table before allocating its replacement
+                self.tt = Arc::new(TranspositionTable::new(MIN_HASH_MB));
+                self.tt = Arc::new(TranspositionTable::new(size_mb));
+                Ok(())
+            }
+            "clear hash" => {
+                self.tt.clear();
+                Ok(())
+            }
+            "move overhead" => {
+                let millis: u64 = parse_value(&name, value.as_deref())?;
+                if millis > MAX_MOVE_OVERHEAD_MS {
+                    return Err(UciError::InvalidValue(name, millis.to_string()));
+                }
+
+                self.move_overhead = Duration::from_millis(millis);
+                Ok(())
+            }
+            // Pondering is driven entirely by the GUI, so there is nothing to configure
+            "ponder" => Ok(()),
+            "uci_chess960" => {
+                self.chess960 = parse_value(&name, value.as_deref())?;
+                self.position.set_chess960(self.chess960);
+                Ok(())
+            }
+            _ => {
+                let (_, enabled) = self
+                    .search_options
+                    .toggles()
+                    .into_iter()
+                    .find(|(option, _)| option.eq_ignore_ascii_case(&name))
+                    .ok_or_else(|| UciError::UnknownOption(name.clone()))?;
+                *enabled = parse_value(&name, value.as_deref())?;
+                Ok(())
+            }
+        }
+    }
+
+    fn start_search(&mut self, params: GoParams) {
+        let signals = SearchSignals::new(params.ponder);
+        let mut position = self.position.clone();
+        let chess960 = position.is_chess960();
+        let limits = SearchLimits {
+            move_overhead: self.move_overhead,
+            ..params.limits(position.side_to_move())
+        };
+        let tt = self.tt.clone();
+        let options = self.search_options;
+        let history = self.history.clone();
+
+        let thread = {
+            let signals = signals.clone();
+            thread::spawn(move || {
+                let result = Searcher::new(limits, signals.clone(), tt)
+                    .with_options(options)
+                    .with_root_moves(params.search_moves)
+                    .with_history(history)
+                    .search(&mut position, |info| {
+                        println!("{}", format_info(info, chess960))
+                    });
+
+                // The best move may not be reported until the GUI ends an infinite or ponder search
+                while (params.infinite || signals.is_pondering()) && !signals.is_stopped() {
+                    thread::sleep(IDLE_POLL_INTERVAL);
+                }
+
+                match (result.best_move, result.ponder_move) {
+                    (Some(best), Some(ponder)) => println!(
+                        "bestmove {} ponder {}",
+                        best.to_uci(chess960),
+                        ponder.to_uci(chess960)
+                    ),
+                    (Some(best), None) => println!("bestmove {}", best.to_uci(chess960)),
+                    (None, _) => println!("bestmove 0000"),
+                }
+            })
+        };
+
+        self.search = Some(SearchHandle { signals, thread });
+    }
+
+    fn stop_search(&mut self) {
+        if let Some(search) = self.search.take() {
+            search.stop();
+        }
+    }
+}
+
+impl Default for Uci {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Parses the arguments of a `position` command into the resulting position and its history.
+///
+/// With `chess960` set, castling moves are read as the king taking its own rook.
+fn parse_position(
+    mut tokens: SplitWhitespace,
+    chess960: bool,
+) -> Result<(Position, Vec<State>), UciError> {
+    let mut position = match tokens.next() {
+        Some("startpos") => {
+            let position = Position::from_str(STARTING_FEN)
+                .map_err(|err| UciError::InvalidFen(STARTING_FEN.to_string(), err))?;
+            if let Some(token) = tokens.next()
+                && token != "moves"
+            {
+                return Err(UciError::InvalidValue(
+                    "position".to_string(),
+                    token.to_string(),
+                ));
+            }
+
+            position
+        }
+        Some("fen") => {
+            let fen = tokens
+                .by_ref()
+                .take_while(|&token| token != "moves")
+                .collect::<Vec<_>>()
+                .join(" ");
+            Position::from_str(&fen).map_err(|err| UciError::InvalidFen(fen, err))?
+        }
+        Some(token) => {
+            return Err(UciError::InvalidValue(
+                "position".to_string(),
+                token.to_string(),
+            ));
+        }
+        None => return Err(UciError::MissingValue("position".to_string())),
+    };
+
+    if chess960 {
+        position.set_chess960(true);
+    }
+
+    let mut history = Vec::new();
+    for move_str in tokens {
+        let mv = Move::from_uci(move_str, &position).map_err(UciError::IllegalMove)?;
+        position.make_move(mv, &mut history);
+    }
+
+    Ok((position, history))
+}
+
+/// Parses the arguments of a `go` command.
+fn parse_go(mut tokens: SplitWhitespace, position: &Position) -> Result<GoParams, UciError> {
+    let mut params = GoParams::default();
+    while let Some(token) = tokens.next() {
+        match token {
+            "searchmoves" => {
+                // Search moves run until the next recognized keyword, so peek before consuming
+                let mut remaining = tokens.clone();
+                while let Some(move_str) = remaining.next() {
+                    match Move::from_uci(move_str, position) {
+                        Ok(mv) => {
+                            params.search_moves.push(mv);
+                            tokens = remaining.clone();
+                        }
+                        Err(_) => break,
+                    }
+                }
+            }
+            "ponder" => params.ponder = true,
+            "infinite" => params.infinite = true,
+            "wtime" => params.white_time = Some(parse_millis(token, tokens.next())?),
+            "btime" => params.black_time = Some(parse_millis(token, tokens.next())?),
+            "winc" => params.white_increment = Some(parse_millis(token, tokens.next())?),
+            "binc" => params.black_increment = Some(parse_millis(token, tokens.next())?),
+            "movetime" => params.move_time = Some(parse_millis(token, tokens.next())?),
+            "movestogo" => params.moves_to_go = Some(parse_value(token, tokens.next())?),
+            "depth" => params.depth = Some(parse_value(token, tokens.next())?),
+            "nodes" => params.nodes = Some(parse_value(token, tokens.next())?),
+            "mate" => params.mate = Some(parse_value(token, tokens.next())?),
+            _ => return Err(UciError::InvalidValue("go".to_string(), token.to_string())),
+        }
+    }
+
+    Ok(params)
+}
+
+/// Splits the arguments of a `setoption` command into the option name and optional value.
+fn parse_set_option(mut tokens: SplitWhitespace) -> Result<(String, Option<String>), UciError> {
+    if tokens.next() != Some("name") {
+        return Err(UciError::MissingValue("name".to_string()));
+    }
+
+    let mut name = Vec::new();
+    let mut value = None;
+    for token in tokens.by_ref() {
+        if token == "value" {
+            value = Some(tokens.collect::<Vec<_>>().join(" "));
+            break;
+        }
+
+        name.push(token);
+    }
+
+    if name.is_empty() {
+        return Err(UciError::MissingValue("name".to_string()));
+    }
+
+    Ok((name.join(" "), value))
+}
+
+fn parse_value<T: FromStr>(name: &str, value: Option<&str>) -> Result<T, UciError> {
+    let value = value.ok_or_else(|| UciError::MissingValue(name.to_string()))?;
+    value
+        .parse()
+        .map_err(|_| UciError::InvalidValue(name.to_string(), value.to_string()))
+}
+
+fn parse_millis(name: &str, value: Option<&str>) -> Result<Duration, UciError> {
+    // GUIs may send negative clock times when a player has flagged
+    let millis: i64 = parse_value(name, value)?;
+    Ok(Duration::from_millis(millis.max(0) as u64))
+}
+
+/// Formats the summary of a search iteration as a UCI `info` line.
+fn format_info(info: &SearchInfo, chess960: bool) -> String {
+    let score = match info.score {
+        Score::Centipawns(cp) => format!("cp {cp}"),
+        Score::Mate(moves) => format!("mate {moves}"),
+    };
+    let bound = match info.bound {
+        Bound::Exact => "",
+        Bound::Lower => " lowerbound",
+        Bound::Upper => " upperbound",
+    };
+    let millis = info.time.as_millis();
+    let nps = info.nodes as u128 * 1000 / millis.max(1);
+    let pv = info
+        .pv
+        .iter()
+        .map(|mv| mv.to_uci(chess960))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    format!(
+        "info depth {} seldepth {} score {score}{bound} nodes {} nps {nps} hashfull {} time {millis} pv {pv}",
+        info.depth, info.seldepth, info.nodes, info.hashfull
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chess::{Square, movegen::MoveKind};
+
+    use super::*;
+
+    #[test]
+    fn test_parse_position_startpos_with_moves() {
+        let (position, history) =
+            parse_position("startpos moves e2e4 e7e5 g1f3".split_whitespace(), false)
+                .expect("failed to parse position");
+        let expected: Position = "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2"
+            .parse()
+            .unwrap();
+
+        assert_eq!(expected, position);
+        assert_eq!(3, history.len());
+    }
+
+    #[test]
+    fn test_parse_position_fen() {
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        let command = format!("fen {fen} moves e1g1");
+        let (position, _) = parse_position(command.split_whitespace(), false).unwrap();
+        let expected: Position =
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R4RK1 b kq - 1 1"
+                .parse()
+                .unwrap();
+
+        assert_eq!(expected, position);
+    }
+
+    #[test]
+    fn test_parse_position_chess960() {
+        let command = "fen 1r2k1r1/pppppppp/8/8/8/8/PPPPPPPP/1R2K1R1 w GBgb - 0 1 moves e1g1 e8b8";
+        let (position, history) = parse_position(command.split_whitespace(), true).unwrap();
+
+        assert!(position.is_chess960());
+        assert_eq!(2, history.len());
+        assert_eq!(
+            "2kr2r1/pppppppp/8/8/8/8/PPPPPPPP/1R3RK1 w - - 2 2",
+            position.to_fen()
+        );
+    }
+
+    #[test]
+    fn test_parse_position_illegal_move() {
+        let result = parse_position("startpos moves e2e5".split_whitespace(), false);
+        assert!(matches!(result, Err(UciError::IllegalMove(_))));
+    }
+
+    #[test]
+    fn test_parse_go() {
+        let position = Position::default();
+        let params = parse_go(
+            "wtime 60000 btime 59000 winc 1000 binc 1000 movestogo 20".split_whitespace(),
+            &position,
+        )
+        .unwrap();
+
+        assert_eq!(Some(Duration::from_secs(60)), params.white_time);
+        assert_eq!(Some(Duration::from_secs(59)), params.black_time);
+        assert_eq!(Some(Duration::from_secs(1)), params.white_increment);
+        assert_eq!(Some(Duration::from_secs(1)), params.black_increment);
+        assert_eq!(Some(20), params.moves_to_go);
+        assert!(!params.infinite);
+    }
+
+    #[test]
+    fn test_parse_go_search_moves() {
+        let position = Position::default();
+        let params = parse_go(
+            "searchmoves e2e4 d2d4 depth 5".split_whitespace(),
+            &position,
+        )
+        .unwrap();
+
+        assert_eq!(
+            vec![
+                Move::new(Square::E2, Square::E4, MoveKind::Quiet),
+                Move::new(Square::D2, Square::D4, MoveKind::Quiet)
+            ],
+            params.search_moves
+        );
+        assert_eq!(Some(5), params.depth);
+    }
+
+    #[test]
+    fn test_go_limits() {
+        let position = Position::default();
+        let params = parse_go(
+            "wtime 60000 btime 30000 movetime 500".split_whitespace(),
+            &position,
+        )
+        .unwrap();
+        assert_eq!(
+            Some(Duration::from_millis(500)),
+            params.limits(Color::White).move_time
+        );
+
+        let params = parse_go(
+            "wtime 60000 btime 30000 binc 500 movestogo 20".split_whitespace(),
+            &position,
+        )
+        .unwrap();
+        assert_eq!(None, params.limits(Color::White).move_time);
+        assert_eq!(
+            Some(Clock {
+                time: Duration::from_secs(60),
+                increment: Duration::ZERO,
+                moves_to_go: Some(20),
+            }),
+            params.limits(Color::White).clock
+        );
+        assert_eq!(
+            Some(Clock {
+                time: Duration::from_secs(30),
+                increment: Duration::from_millis(500),
+                moves_to_go: Some(20),
+            }),
+            params.limits(Color::Black).clock
+        );
+    }
+
+    #[test]
+    fn test_move_overhead_option() {
+        let mut uci = Uci::new();
+        assert_eq!(
+            Duration::from_millis(DEFAULT_MOVE_OVERHEAD_MS),
+            uci.move_overhead
+        );
+
+        uci.handle_command("setoption name Move Overhead value 30")
+            .unwrap();
+        assert_eq!(Duration::from_millis(30), uci.move_overhead);
+        assert!(matches!(
+            uci.handle_command("setoption name Move Overhead value 6000"),
+            Err(UciError::InvalidValue(..))
+        ));
+    }
+
+    #[test]
+    fn test_parse_set_option() {
+        let (name, value) =
+            parse_set_option("name Move Overhead value 30".split_whitespace()).unwrap();
+        assert_eq!("Move Overhead", name);
+        assert_eq!(Some("30".to_string()), value);
+
+        let (name, value) = parse_set_option("name Clear Hash".split_whitespace()).unwrap();
+        assert_eq!("Clear Hash", name);
+        assert_eq!(None, value);
+    }
+
+    #[test]
+    fn test_format_info() {
+        let mut info = SearchInfo {
+            depth: 5,
+            seldepth: 9,
+            nodes: 3_000,
+            qnodes: 1_000,
+            time: Duration::from_millis(1_500),
+            score: Score::Centipawns(-20),
+            bound: Bound::Exact,
+            hashfull: 12,
+            pv: vec![
+                Move::new(Square::E2, Square::E4, MoveKind::Quiet),
+                Move::new(Square::E7, Square::E5, MoveKind::Quiet),
+            ],
+        };
+        assert_eq!(
+            "info depth 5 seldepth 9 score cp -20 nodes 3000 nps 2000 hashfull 12 time 1500 pv e2e4 e7e5",
+            format_info(&info, false)
+        );
+
+        info.score = Score::Mate(3);
+        info.bound = Bound::Lower;
+        assert!(format_info(&info, false).contains("score mate 3 lowerbound nodes"));
+        info.bound = Bound::Upper;
+        assert!(format_info(&info, false).contains("score mate 3 upperbound nodes"));
+    }
+
+    #[test]
+    fn test_search_option_toggles() {
+        let mut uci = Uci::new();
+        uci.handle_command("setoption name LateMoveReductions value false")
+            .unwrap();
+        uci.handle_command("setoption name razoring value false")
+            .unwrap();
+
+        assert!(!uci.search_options.late_move_reductions);
+        assert!(!uci.search_options.razoring);
+        assert!(uci.search_options.null_move_pruning);
+        assert!(matches!(
+            uci.handle_command("setoption name Razoring value maybe"),
+            Err(UciError::InvalidValue(..))
+        ));
+        assert!(matches!(
+            uci.handle_command("setoption name Pruning value false"),
+            Err(UciError::UnknownOption(_))
+        ));
+    }
+}